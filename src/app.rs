@@ -1,45 +1,156 @@
+use crate::due::{Date, Due, Now, Time};
+use crate::events::{Action, EventLog, LOG_KEY};
+use crate::history::{History, Snapshot, HISTORY_KEY};
+use crate::listener::WindowListener;
+use crate::model::{EntryId, ListId, Priority, SortOrder, State};
+use crate::quick_add::QuickAdd;
+use crate::route::{HashListener, Route};
+use crate::schema::{self, Persisted};
+use crate::session::{Session, SESSION_KEY};
+use crate::storage::{self, Backend, IdbStore, Storage, Stored};
+use crate::validation::{self, Invalid};
+use crate::view::{self, entry_element_id, NEW_TODO_ID};
 use log::*;
-use serde_derive::{Deserialize, Serialize};
-use strum::IntoEnumIterator;
-use strum_macros::{EnumIter, ToString};
-use yew::format::Json;
+use std::time::Duration;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlElement, HtmlInputElement};
 use yew::prelude::*;
-use yew::services::storage::{Area, StorageService};
+use yew::services::interval::{IntervalService, IntervalTask};
+use yew::services::reader::{File, FileData, ReaderService, ReaderTask};
 
 const KEY: &str = "yew.todomvc.self";
+/// Where an unreadable payload is kept before anything overwrites `KEY`.
+const BACKUP_KEY: &str = "yew.todomvc.self.backup";
+/// Undo group for typing into the new todo input.
+const DRAFT_GROUP: &str = "draft";
 
 pub struct App {
     link: ComponentLink<Self>,
-    storage: StorageService,
+    storage: Box<dyn Storage>,
+    /// Set once IndexedDB has loaded, if it is the preferred backend. Entries
+    /// are then persisted there instead of in `storage`.
+    idb: Option<IdbStore>,
+    /// Whether entries are still being loaded from IndexedDB.
+    loading: bool,
     state: State,
+    /// What to show once the entries are loaded, until they are.
+    startup: Option<Startup>,
+    recovery: Option<Recovery>,
+    /// The backend that was asked for, if it couldn't be used.
+    unavailable_backend: Option<Backend>,
+    /// The last failed write, e.g. because the storage quota is exceeded.
+    storage_error: Option<String>,
+    /// The entry the current route links to.
+    linked: Option<EntryId>,
+    /// Whether the linked entry still needs to be scrolled into view.
+    scroll_to_linked: bool,
+    /// The input shown while an entry is edited.
+    edit_input: NodeRef,
+    /// The input shown while a list is renamed.
+    rename_input: NodeRef,
+    focus: Option<Focus>,
+    /// Kept in IndexedDB if it is used, and in `storage` otherwise.
+    history: History<Snapshot>,
+    /// Whether `history` changed since it was last stored.
+    history_dirty: bool,
+    /// Kept in IndexedDB if it is used, and only for the session otherwise.
+    log: EventLog,
+    /// Why the last event log import failed.
+    log_error: Option<String>,
+    reader: ReaderService,
+    import_task: Option<ReaderTask>,
+    #[cfg(feature = "devtools")]
+    devtools: crate::devtools::Devtools,
+    _hash_listener: HashListener,
+    _key_listener: WindowListener,
+    _clock: IntervalTask,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct State {
-    entries: Vec<Entry>,
-    filter: Filter,
-    value: String,
-    edit_value: String,
+/// The session and route the app was opened with. They refer to lists and
+/// entries, so they are only applied once those are loaded.
+struct Startup {
+    session: Session,
+    /// The route in the URL, if there was one.
+    route: Option<Route>,
 }
 
-#[derive(Serialize, Deserialize)]
-struct Entry {
-    description: String,
-    completed: bool,
-    editing: bool,
+/// Details of a stored payload that could not be read, shown in a banner
+/// until the user dismisses it or resets the storage.
+struct Recovery {
+    raw: String,
+    error: String,
+    salvaged: usize,
+    /// Whether `raw` is kept somewhere else than `KEY`, either under
+    /// `BACKUP_KEY` or downloaded. Until it is, `KEY` isn't overwritten.
+    backed_up: bool,
 }
 
+/// Where to move the keyboard focus once the next render is done.
+enum Focus {
+    /// The edit input, with its text selected.
+    EditInput,
+    /// An entry's list item, after editing or moving it.
+    Entry(EntryId),
+    /// The input of the list being renamed, with its text selected.
+    RenameInput,
+}
+
+#[derive(Debug)]
 pub enum Msg {
     Add,
-    Edit(usize),
+    Edit(EntryId),
     Update(String),
     UpdateEdit(String),
-    Remove(usize),
-    SetFilter(Filter),
+    Remove(EntryId),
+    SetRoute(Route),
     ToggleAll,
-    ToggleEdit(usize),
-    Toggle(usize),
+    ToggleEdit(EntryId),
+    CancelEdit(EntryId),
+    SetDue(EntryId, Option<Due>),
+    SetSort(SortOrder),
+    SetPriority(EntryId, Priority),
+    SetPriorityFilter(Option<Priority>),
+    AddTag(EntryId, String),
+    RemoveTag(EntryId, String),
+    SetContext(EntryId, Option<String>),
+    AddSubtask(EntryId, String),
+    /// Toggles the subtask with the second id of the entry with the first.
+    ToggleSubtask(EntryId, EntryId),
+    RemoveSubtask(EntryId, EntryId),
+    SetAutoComplete(EntryId, bool),
+    /// Hides or shows the subtasks of an entry.
+    ToggleCollapsed(EntryId),
+    AddList(String),
+    StartRename(ListId),
+    RenameList(ListId, String),
+    CancelRename,
+    RemoveList(ListId),
+    MoveEntry(EntryId, ListId),
+    /// Moves an entry above the visible one before it, or below the one
+    /// after it if set.
+    Reorder(EntryId, bool),
+    DragStart(EntryId),
+    /// Drops the dragged entry onto the entry with this id.
+    Drop(EntryId),
+    DragEnd,
+    /// Sent every minute, as due entries may have become overdue.
+    Tick,
+    Toggle(EntryId),
     ClearCompleted,
+    DownloadBackup,
+    DismissRecovery,
+    ResetStorage,
+    IdbLoaded(Result<(IdbStore, Option<Box<Stored>>), String>),
+    StorageFailed(String),
+    DismissStorageError,
+    ResetSession,
+    Undo,
+    Redo,
+    ExportLog,
+    ImportLog(File),
+    LogImported(FileData),
+    #[cfg(feature = "devtools")]
+    Devtools(crate::devtools::Command),
     Nope,
 }
 
@@ -48,24 +159,166 @@ impl Component for App {
     type Properties = ();
 
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let storage = StorageService::new(Area::Local).unwrap();
-        let entries = {
-            if let Json(Ok(restored_entries)) = storage.restore(KEY) {
-                restored_entries
+        let preferred = storage::preferred();
+        let mut storage = storage::open(preferred);
+        let mut unavailable_backend =
+            Some(preferred).filter(|&b| b != Backend::IndexedDb && b != storage.backend());
+        let mut loading = false;
+        if preferred == Backend::IndexedDb {
+            if IdbStore::is_supported() {
+                loading = true;
+                let link = link.clone();
+                wasm_bindgen_futures::spawn_local(async move {
+                    let opened = IdbStore::open().await;
+                    let opened = opened.map(|(idb, stored)| (idb, stored.map(Box::new)));
+                    link.send_message(Msg::IdbLoaded(opened));
+                });
             } else {
-                Vec::new()
+                unavailable_backend = Some(Backend::IndexedDb);
+            }
+        }
+        let restored = storage.load(KEY).map(|raw| {
+            let decoded = schema::decode(&raw);
+            (raw, decoded)
+        });
+        let mut recovery = None;
+        let persisted = match restored {
+            Some((raw, Ok(persisted))) => {
+                // Write migrated payloads back in the current format.
+                let encoded =
+                    schema::encode(persisted.next_id, &persisted.lists, &persisted.entries);
+                if encoded != raw {
+                    if let Err(err) = storage.store(KEY, &encoded) {
+                        warn!("Couldn't migrate entries: {}", err);
+                    }
+                }
+                persisted
             }
+            Some((raw, Err(err))) => {
+                warn!("Couldn't restore entries: {}", err);
+                let backed_up = match storage.store(BACKUP_KEY, &raw) {
+                    Ok(()) => true,
+                    Err(err) => {
+                        warn!("Couldn't back up unreadable entries: {}", err);
+                        false
+                    }
+                };
+                let persisted = schema::recover(&raw);
+                recovery = Some(Recovery {
+                    raw,
+                    error: err.to_string(),
+                    salvaged: persisted.entries.len(),
+                    backed_up,
+                });
+                persisted
+            }
+            None => Persisted::default(),
         };
-        let state = State {
-            entries,
-            filter: Filter::All,
-            value: "".into(),
-            edit_value: "".into(),
+        let session = storage
+            .load(SESSION_KEY)
+            .map(|raw| Session::decode(&raw))
+            .unwrap_or_default();
+        let startup = Startup {
+            session,
+            route: Some(Route::current()).filter(|_| !Route::is_unset()),
         };
-        App {
+        let mut state = State::new(persisted);
+        state.now = local_now();
+        let history = storage
+            .load(HISTORY_KEY)
+            .and_then(|raw| History::decode(&raw).ok())
+            .unwrap_or_default();
+        let log = EventLog::new(state.to_persisted());
+        storage.remove(LOG_KEY);
+        let hash_listener = HashListener::new(link.callback(Msg::SetRoute));
+        let clock = IntervalService::spawn(Duration::from_secs(60), link.callback(|_| Msg::Tick));
+        let on_shortcut = link.callback(|redo| if redo { Msg::Redo } else { Msg::Undo });
+        let key_listener = WindowListener::new("keydown", move |e| {
+            let e = match e.dyn_into::<KeyboardEvent>() {
+                Ok(e) if e.ctrl_key() || e.meta_key() => e,
+                _ => return,
+            };
+            // Ctrl+Z undoes, Ctrl+Shift+Z and Ctrl+Y redo.
+            let redo = match e.key().to_lowercase().as_str() {
+                "z" => e.shift_key(),
+                "y" => true,
+                _ => return,
+            };
+            // Other text inputs keep the browser's own undo for their text.
+            // The new todo's draft is part of the history instead.
+            let editing_text = e
+                .target()
+                .and_then(|target| target.dyn_into::<HtmlInputElement>().ok())
+                .is_some_and(|input| input.type_() == "text" && input.id() != NEW_TODO_ID);
+            if editing_text {
+                return;
+            }
+            e.prevent_default();
+            on_shortcut.emit(redo);
+        });
+        let mut app = App {
             link,
             storage,
+            idb: None,
+            loading,
             state,
+            startup: Some(startup),
+            recovery,
+            unavailable_backend,
+            storage_error: None,
+            linked: None,
+            scroll_to_linked: false,
+            edit_input: NodeRef::default(),
+            rename_input: NodeRef::default(),
+            focus: None,
+            history,
+            history_dirty: false,
+            log,
+            log_error: None,
+            reader: ReaderService::new(),
+            import_task: None,
+            #[cfg(feature = "devtools")]
+            devtools: Default::default(),
+            _hash_listener: hash_listener,
+            _key_listener: key_listener,
+            _clock: clock,
+        };
+        if !app.loading {
+            app.start();
+        }
+        app
+    }
+
+    fn rendered(&mut self, _first_render: bool) {
+        if self.loading {
+            return;
+        }
+        match self.focus.take() {
+            Some(Focus::EditInput) => {
+                if let Some(input) = self.edit_input.cast::<HtmlInputElement>() {
+                    let _ = input.focus();
+                    input.select();
+                }
+            }
+            Some(Focus::RenameInput) => {
+                if let Some(input) = self.rename_input.cast::<HtmlInputElement>() {
+                    let _ = input.focus();
+                    input.select();
+                }
+            }
+            Some(Focus::Entry(id)) => {
+                let item = entry_element(id).and_then(|e| e.dyn_into::<HtmlElement>().ok());
+                if let Some(item) = item {
+                    let _ = item.focus();
+                }
+            }
+            None => {}
+        }
+        if self.scroll_to_linked {
+            self.scroll_to_linked = false;
+            if let Some(element) = self.linked.and_then(entry_element) {
+                element.scroll_into_view();
+            }
         }
     }
 
@@ -74,96 +327,415 @@ impl Component for App {
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
+        #[cfg(feature = "devtools")]
+        let recorded = (format!("{:?}", msg), self.state_json());
+        // Which changes can be undone, and the undo group they belong to.
+        let undo_group = match msg {
+            Msg::Update(_) => Some(Some(DRAFT_GROUP)),
+            Msg::Add
+            | Msg::Edit(_)
+            | Msg::Remove(_)
+            | Msg::ToggleAll
+            | Msg::Toggle(_)
+            | Msg::SetDue(..)
+            | Msg::SetPriority(..)
+            | Msg::AddTag(..)
+            | Msg::RemoveTag(..)
+            | Msg::SetContext(..)
+            | Msg::AddSubtask(..)
+            | Msg::ToggleSubtask(..)
+            | Msg::RemoveSubtask(..)
+            | Msg::SetAutoComplete(..)
+            | Msg::AddList(_)
+            | Msg::RenameList(..)
+            | Msg::RemoveList(_)
+            | Msg::MoveEntry(..)
+            | Msg::Reorder(..)
+            | Msg::Drop(_)
+            | Msg::ClearCompleted
+            | Msg::ResetStorage
+            | Msg::LogImported(_) => Some(None),
+            _ => None,
+        };
+        // While the devtools show an earlier state, only changes to it move
+        // on from there; anything else just looks around in it.
+        #[cfg(feature = "devtools")]
+        let moves_on = undo_group.is_some() || matches!(msg, Msg::Undo | Msg::Redo);
+        #[cfg(feature = "devtools")]
+        if moves_on && self.devtools.shows_past() {
+            self.continue_from_shown();
+        }
+        let before = undo_group.map(|_| Snapshot::of(&self.state));
         match msg {
             Msg::Add => {
-                let entry = Entry {
-                    description: self.state.value.clone(),
-                    completed: false,
-                    editing: false,
-                };
-                self.state.entries.push(entry);
-                self.state.value = "".to_string();
+                let parsed = QuickAdd::parse(&self.state.value, &self.state.now);
+                match validation::description(&parsed.description) {
+                    Ok(description) => {
+                        let id = self.state.allocate_id();
+                        self.state.value.clear();
+                        let parsed = QuickAdd {
+                            description,
+                            ..parsed
+                        };
+                        for action in parsed.actions(id, self.state.list) {
+                            self.dispatch(action);
+                        }
+                    }
+                    Err(invalid) => self.state.value_error = Some(invalid),
+                }
             }
-            Msg::Edit(idx) => {
-                let edit_value = self.state.edit_value.clone();
-                self.state.complete_edit(idx, edit_value);
-                self.state.edit_value = "".to_string();
+            // Enter and blur both save, so the second one finds the edit
+            // already finished and is ignored.
+            Msg::Edit(id) if self.state.editing == Some(id) => {
+                match validation::description(&self.state.edit_value) {
+                    Ok(description) => self.dispatch(Action::Edit { id, description }),
+                    // Clearing the description removes the todo.
+                    Err(Invalid::Empty) => self.dispatch(Action::Remove { id }),
+                    Err(invalid) => self.state.edit_error = Some(invalid),
+                }
+                if self.state.edit_error.is_none() {
+                    self.state.editing = None;
+                    self.state.edit_value.clear();
+                    self.focus = Some(Focus::Entry(id));
+                }
             }
+            Msg::Edit(_) => return false,
             Msg::Update(val) => {
                 println!("Input: {}", val);
                 self.state.value = val;
+                self.state.value_error = None;
             }
             Msg::UpdateEdit(val) => {
                 println!("Input: {}", val);
                 self.state.edit_value = val;
+                self.state.edit_error = None;
             }
-            Msg::Remove(idx) => {
-                self.state.remove(idx);
+            Msg::Remove(id) => {
+                self.dispatch(Action::Remove { id });
             }
-            Msg::SetFilter(filter) => {
-                self.state.filter = filter;
+            Msg::SetRoute(route) => match &mut self.startup {
+                // Shown once the entries are loaded.
+                Some(startup) => startup.route = Some(route),
+                None => {
+                    route.show_in(&mut self.state);
+                    route.add_missing_sort(&self.state);
+                    self.linked = route.entry;
+                    self.scroll_to_linked = route.entry.is_some();
+                }
+            },
+            Msg::ToggleEdit(id) => {
+                if let Some(entry) = self.state.entry(id) {
+                    self.state.edit_value = entry.description.clone();
+                }
+                self.state.toggle_edit(id);
+                self.focus = Some(if self.state.editing == Some(id) {
+                    Focus::EditInput
+                } else {
+                    Focus::Entry(id)
+                });
             }
-            Msg::ToggleEdit(idx) => {
-                self.state.edit_value = self.state.entries[idx].description.clone();
-                self.state.toggle_edit(idx);
+            Msg::CancelEdit(id) if self.state.editing == Some(id) => {
+                self.state.toggle_edit(id);
+                self.state.edit_value.clear();
+                self.focus = Some(Focus::Entry(id));
             }
+            Msg::CancelEdit(_) => return false,
             Msg::ToggleAll => {
-                let status = !self.state.is_all_completed();
-                self.state.toggle_all(status);
+                self.dispatch(self.state.toggle_visible());
+            }
+            Msg::SetDue(id, due) => {
+                self.dispatch(Action::SetDue { id, due });
+            }
+            Msg::SetSort(order) => {
+                self.state.set_sort(order);
+                Route::of(&self.state).replace();
+            }
+            Msg::SetPriority(id, priority) => {
+                self.dispatch(Action::SetPriority { id, priority });
+            }
+            Msg::SetPriorityFilter(priority) => {
+                self.state.priority_filter = priority;
+            }
+            Msg::AddTag(id, tag) => {
+                if let Some(tag) = validation::tag(&tag) {
+                    self.dispatch(Action::AddTag { id, tag });
+                }
+            }
+            Msg::RemoveTag(id, tag) => {
+                self.dispatch(Action::RemoveTag { id, tag });
+            }
+            Msg::SetContext(id, context) => {
+                self.dispatch(Action::SetContext { id, context });
+            }
+            Msg::AddSubtask(id, description) => {
+                // Like tags, a subtask that can't be added is just not added.
+                if let Ok(description) = validation::description(&description) {
+                    let subtask = self.state.allocate_id();
+                    self.dispatch(Action::AddSubtask {
+                        id,
+                        subtask,
+                        description,
+                    });
+                }
             }
-            Msg::Toggle(idx) => {
-                self.state.toggle(idx);
+            Msg::ToggleSubtask(id, subtask) => {
+                self.dispatch(Action::ToggleSubtask { id, subtask });
+            }
+            Msg::RemoveSubtask(id, subtask) => {
+                self.dispatch(Action::RemoveSubtask { id, subtask });
+            }
+            Msg::SetAutoComplete(id, auto_complete) => {
+                self.dispatch(Action::SetAutoComplete { id, auto_complete });
+            }
+            Msg::ToggleCollapsed(id) => {
+                if !self.state.collapsed.remove(&id) {
+                    self.state.collapsed.insert(id);
+                }
+            }
+            Msg::AddList(name) => {
+                if let Some(name) = validation::list_name(self.state.lists(), &name, None) {
+                    let id = self.state.allocate_id();
+                    self.dispatch(Action::AddList { id, name });
+                    // Show the new list, as there is nothing else to do
+                    // with it.
+                    self.state.list = id;
+                    self.state.tag_filter = None;
+                    Route::of(&self.state).replace();
+                }
+            }
+            Msg::StartRename(id) => {
+                self.state.renaming = Some(id);
+                self.focus = Some(Focus::RenameInput);
+            }
+            Msg::RenameList(id, name) => {
+                // An invalid name keeps the input open.
+                if let Some(name) = validation::list_name(self.state.lists(), &name, Some(id)) {
+                    self.state.renaming = None;
+                    self.dispatch(Action::RenameList { id, name });
+                    // Links to the old name no longer work.
+                    if id == self.state.list {
+                        Route::of(&self.state).replace();
+                    }
+                }
+            }
+            Msg::CancelRename => {
+                self.state.renaming = None;
+            }
+            Msg::RemoveList(id) => {
+                let shown = self.state.list;
+                self.dispatch(Action::RemoveList { id });
+                // Removing the shown list shows another one.
+                if self.state.list != shown {
+                    Route::of(&self.state).replace();
+                }
+            }
+            Msg::MoveEntry(id, list) => {
+                self.dispatch(Action::MoveEntry { id, list });
+            }
+            Msg::Reorder(id, below) => match self.state.move_past(id, below) {
+                Some(action) => {
+                    self.dispatch(action);
+                    self.focus = Some(Focus::Entry(id));
+                }
+                None => return false,
+            },
+            Msg::DragStart(id) => {
+                self.state.dragging = Some(id);
+            }
+            Msg::Drop(target) => {
+                let id = self.state.dragging.take();
+                if let Some(action) = id.and_then(|id| self.state.drop_onto(id, target)) {
+                    self.dispatch(action);
+                }
+            }
+            Msg::DragEnd => {
+                self.state.dragging = None;
+            }
+            Msg::Tick => {
+                self.state.now = local_now();
+                return true;
+            }
+            Msg::Toggle(id) => {
+                self.dispatch(Action::Toggle { id });
             }
             Msg::ClearCompleted => {
-                self.state.clear_completed();
+                self.dispatch(Action::ClearCompleted {
+                    list: Some(self.state.list),
+                });
+            }
+            Msg::DownloadBackup => {
+                if let Some(recovery) = &mut self.recovery {
+                    match download("todos-backup.json", &recovery.raw) {
+                        Some(()) => recovery.backed_up = true,
+                        None => warn!("Couldn't download the backup"),
+                    }
+                }
+            }
+            Msg::DismissRecovery => {
+                self.recovery = None;
+            }
+            Msg::ResetStorage => {
+                self.recovery = None;
+                self.dispatch(Action::Replace {
+                    entries: Vec::new(),
+                    lists: Vec::new(),
+                });
+                self.storage.remove(BACKUP_KEY);
+            }
+            Msg::ResetSession => {
+                self.state.restore_session(Session::default());
+                Route::default().replace();
+            }
+            Msg::Undo => {
+                if let Some(snapshot) = self.history.undo(&Snapshot::of(&self.state)) {
+                    self.restore(snapshot);
+                }
+            }
+            Msg::Redo => {
+                if let Some(snapshot) = self.history.redo(&Snapshot::of(&self.state)) {
+                    self.restore(snapshot);
+                }
+            }
+            Msg::ExportLog => {
+                // Encoded only now, as the log can be large.
+                if download("todos-events.json", &self.log.encode()).is_none() {
+                    warn!("Couldn't export the event log");
+                }
+                return false;
+            }
+            Msg::ImportLog(file) => {
+                let callback = self.link.callback(Msg::LogImported);
+                match self.reader.read_file(file, callback) {
+                    Ok(task) => self.import_task = Some(task),
+                    Err(err) => self.log_error = Some(err.to_string()),
+                }
+            }
+            Msg::LogImported(file) => {
+                self.import_task = None;
+                let imported = String::from_utf8(file.content)
+                    .map_err(|err| err.to_string())
+                    .and_then(|raw| EventLog::decode(&raw).map_err(|err| err.to_string()));
+                match imported {
+                    Ok(log) => {
+                        // The imported log replaces ours, so this change
+                        // isn't recorded in it.
+                        self.state.load(log.replay().to_persisted());
+                        self.log = log;
+                        self.log_error = None;
+                        if let Some(idb) = &mut self.idb {
+                            idb.forget_log();
+                        }
+                    }
+                    Err(err) => {
+                        self.log_error = Some(format!("{}: {}", file.name, err));
+                    }
+                }
+            }
+            Msg::IdbLoaded(Ok((idb, stored))) => {
+                self.loading = false;
+                // With nothing in IndexedDB yet, the entries restored from
+                // `storage` are kept and written to it below.
+                if let Some(stored) = stored {
+                    self.state.load(stored.entries);
+                    self.log = stored
+                        .log
+                        .unwrap_or_else(|| EventLog::new(self.state.to_persisted()));
+                    self.history = stored
+                        .history
+                        .and_then(|raw| History::decode(&raw).ok())
+                        .unwrap_or_default();
+                } else {
+                    self.history_dirty = true;
+                }
+                // The history is kept with the entries from now on.
+                self.storage.remove(HISTORY_KEY);
+                self.idb = Some(idb);
+                self.start();
+            }
+            Msg::IdbLoaded(Err(err)) => {
+                warn!("Couldn't open IndexedDB: {}", err);
+                self.loading = false;
+                self.unavailable_backend = Some(Backend::IndexedDb);
+                self.start();
+            }
+            Msg::StorageFailed(err) => {
+                warn!("{}", err);
+                if let Some(idb) = &mut self.idb {
+                    idb.invalidate();
+                    self.history_dirty = true;
+                }
+                self.storage_error = Some(err);
+                return true;
+            }
+            Msg::DismissStorageError => {
+                self.storage_error = None;
+            }
+            #[cfg(feature = "devtools")]
+            Msg::Devtools(command) => {
+                // Showing an earlier state doesn't persist it; the next
+                // change continues from there, see `continue_from_shown`.
+                if let Some(state) = self.devtools.handle(command) {
+                    let now = self.state.now;
+                    self.state = serde_json::from_value(state.clone()).expect("recorded state");
+                    self.state.now = now;
+                }
+                return true;
             }
-            Msg::Nope => {}
+            Msg::Nope => return false,
         }
-        self.storage.store(KEY, Json(&self.state.entries));
+        if let (Some(before), Some(group)) = (before, undo_group) {
+            let after = Snapshot::of(&self.state);
+            if before != after {
+                self.history.record(&before, &after, group);
+                self.history_dirty = true;
+            }
+        }
+        #[cfg(feature = "devtools")]
+        {
+            if !moves_on && self.devtools.shows_past() {
+                return true;
+            }
+            let (msg, before) = recorded;
+            self.devtools.record(msg, before, self.state_json());
+        }
+        self.persist();
         true
     }
 
     fn view(&self) -> Html {
         info!("rendered!");
-        let mut items_left_string = "".to_string();
-        let total = self.state.total();
-        if total == 1 {
-            items_left_string.push_str(" item left")
-        } else {
-            items_left_string.push_str(" items left")
+        if self.loading {
+            return html! {
+                <div class="w-2/3 mx-auto">
+                    <p class="text-center text-slate-500 my-8">{ "Loading your todos…" }</p>
+                </div>
+            };
         }
+        let send = self.link.callback(|msg| msg);
         html! {
             <div class="w-2/3 mx-auto">
-                <section>
-                    <header class="text-center my-4">
-                        <h1 class="text-6xl text-red-600">{ "todos" }</h1>
-                        { self.view_input() }
-                    </header>
-                    <section class="my-4">
-                        <label for="toggle_all" class="block w-full rounded bg-slate-300 mb-4 p-4">
-                            <input id="toggle_all" type="checkbox" checked=self.state.is_all_completed() onclick=self.link.callback(|_| Msg::ToggleAll) />
-                        </label>
-                        <ul>
-                            { for self.state.entries.iter().filter(|e| self.state.filter.fit(e))
-                                .enumerate()
-                                .map(|val| self.view_entry(val)) }
-                        </ul>
-                    </section>
-                    <footer class="flex gap-3 justify-around my-4">
-                        <span class="border-2 rounded p-4">
-                            <strong>{ self.state.total() }</strong>
-                            { items_left_string }
-                        </span>
-                        <ul class="flex-grow gap-3 flex justify-center">
-                            { for Filter::iter().map(|flt| self.view_filter(flt)) }
-                        </ul>
-                        <button class="bg-red-300 hover:bg-red-500 hover:text-white transition-colors rounded p-4" onclick=self.link.callback(|_| Msg::ClearCompleted)>
-                            { format!("Clear completed ({})", self.state.total_completed()) }
-                        </button>
-                    </footer>
-                </section>
+                { self.view_toolbar() }
+                { self.view_storage_notice() }
+                { self.view_storage_error() }
+                { self.view_recovery() }
+                <div class="flex gap-8">
+                    <aside class="w-48 shrink-0">
+                        { view::lists(&self.state, &self.rename_input, &send) }
+                    </aside>
+                    <div class="flex-grow">
+                        { view::todos(&self.state, self.linked, &self.edit_input, &send) }
+                    </div>
+                </div>
+                { self.view_log() }
+                { self.view_devtools() }
                 <footer class="flex flex-col gap-3 items-center text-sm text-slate-500 my-4 mt-8">
-                    <p>{ "Double-click to edit a todo." }</p>
+                    <p>{ "Double-click to edit a todo, Escape to cancel." }</p>
+                    <p>{ "Type #tag, @context, !high or \"tomorrow 5pm\" in a new todo to set them." }</p>
+                    <p>{ "Press 0 to 3 on a selected todo to set its priority." }</p>
+                    <p>{ "Drag a todo, or press Alt+Up or Alt+Down on it, to move it in the list order." }</p>
+                    <p>{ "Double-click a list to rename it." }</p>
+                    <p>{ "Ctrl+Z to undo, Ctrl+Shift+Z to redo." }</p>
+                    <button class="underline" onclick=self.link.callback(|_| Msg::ResetSession)>{ "Reset filter and drafts" }</button>
                     <p>{ "Originally written by " }<a class="underline" href="https://github.com/DenisKolodin/" target="_blank">{ "Denis Kolodin" }</a>{"."}</p>
                     <p>{ "Edited to facilitate tailwindCSS by " }<a class="underline" href="https://blog.vomkonstant.in/" target="_blank">{ "Konstantin Kovar" }</a>{"."}</p>
                 </footer>
@@ -173,197 +745,252 @@ impl Component for App {
 }
 
 impl App {
-    fn view_filter(&self, filter: Filter) -> Html {
-        let flt = filter.clone();
+    /// Changes the entries and records the change in the event log.
+    fn dispatch(&mut self, action: Action) {
+        let at = js_sys::Date::now();
+        self.state.apply(at, &action);
+        self.log.record(at, action);
+    }
 
-        html! {
-            <li class="p-4 border-2 rounded">
-                <a class=if self.state.filter == flt { "selected" } else { "not-selected" }
-                   href=&flt
-                   onclick=self.link.callback(move |_| Msg::SetFilter(flt.clone()))>
-                    { filter }
-                </a>
-            </li>
+    /// Goes back or forward to an undo snapshot.
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.state.value = snapshot.value;
+        let list = self.state.list;
+        self.dispatch(Action::Replace {
+            entries: snapshot.entries,
+            lists: snapshot.lists,
+        });
+        // Undoing the list's creation shows another one.
+        if self.state.list != list {
+            Route::of(&self.state).replace();
         }
+        self.history_dirty = true;
     }
 
-    fn view_input(&self) -> Html {
-        html! {
-            // You can use standard Rust comments. One line:
-            // <li></li>
-            <input class="p-4 w-full mt-4 border-0 border-b-2 border-slate-500 focus:border-slate-800 focus:outline-none"
-                   placeholder="What needs to be done?"
-                   value=&self.state.value
-                   oninput=self.link.callback(|e: InputData| Msg::Update(e.value))
-                   onkeypress=self.link.callback(|e: KeyboardEvent| {
-                       if e.key() == "Enter" { Msg::Add } else { Msg::Nope }
-                   }) />
-            /* Or multiline:
-            <ul>
-                <li></li>
-            </ul>
-            */
+    /// Restores the session and shows the route the app was opened with,
+    /// now that the entries are loaded.
+    fn start(&mut self) {
+        let Startup { session, route } = match self.startup.take() {
+            Some(startup) => startup,
+            None => return,
+        };
+        self.state.restore_session(session);
+        // A route in the URL wins over the restored filter; without one, the
+        // URL is made to match the restored filter instead.
+        match route {
+            Some(route) => {
+                route.show_in(&mut self.state);
+                route.add_missing_sort(&self.state);
+                self.linked = route.entry;
+                self.scroll_to_linked = route.entry.is_some();
+            }
+            None => Route::of(&self.state).replace(),
         }
+        // An edit restored from the session picks up where it was left.
+        self.focus = self.state.editing.map(|_| Focus::EditInput);
     }
 
-    fn view_entry(&self, (idx, entry): (usize, &Entry)) -> Html {
-        let mut label_class = "".to_string();
-        if entry.editing {
-            label_class.push_str(" hidden");
+    fn persist(&mut self) {
+        let history = if self.history_dirty {
+            self.history_dirty = false;
+            Some(self.history.encode())
+        } else {
+            None
+        };
+        // Until the session is restored, the stored one is still current.
+        if self.startup.is_none() {
+            if let Err(err) = self
+                .storage
+                .store(SESSION_KEY, &self.state.session().encode())
+            {
+                warn!("Couldn't save session: {}", err);
+            }
         }
-        if entry.completed {
-            label_class.push_str(" inline");
+        if let Some(idb) = &mut self.idb {
+            let on_error = self.link.callback(Msg::StorageFailed);
+            idb.sync(&self.state, &self.log, history, on_error);
+            return;
         }
-
-        html! {
-            <li class="p-4 pr-0 my-2 border-b-2 border-slate-200 last:border-0">
-                <div class="flex items-center justify-between">
-                <div class="flex gap-6">
-                    <input type="checkbox" checked=entry.completed onclick=self.link.callback(move |_| Msg::Toggle(idx)) />
-                    <label class=label_class ondblclick=self.link.callback(move |_| Msg::ToggleEdit(idx))>{ &entry.description }</label>
-                    { self.view_entry_edit_input((&idx, &entry)) }
-                </div>
-                    <button class="bg-red-300 hover:bg-red-500 hover:text-white transition-colors rounded p-4 ml-auto" onclick=self.link.callback(move |_| Msg::Remove(idx))>{"Remove"}</button>
-                </div>
-            </li>
+        if let Some(history) = history {
+            if let Err(err) = self.storage.store(HISTORY_KEY, &history) {
+                // Losing the history on reload beats failing to save todos.
+                warn!("Couldn't save undo history: {}", err);
+                self.storage.remove(HISTORY_KEY);
+            }
         }
-    }
-
-    fn view_entry_edit_input(&self, (idx, entry): (&usize, &Entry)) -> Html {
-        let idx = *idx;
-        if entry.editing {
-            html! {
-                <input
-                    type="text"
-                    value=self.state.edit_value
-                    oninput=self.link.callback(move |e: InputData| Msg::UpdateEdit(e.value))
-                    onblur=self.link.callback(move |_| Msg::Edit(idx))
-                    onkeypress=self.link.callback(move |e: KeyboardEvent| {
-                        if e.key() == "Enter" { Msg::Edit(idx) } else { Msg::Nope }
-                }) />
+        // The unreadable payload is the only copy until it is backed up.
+        let unsaved = self.recovery.as_ref().is_some_and(|r| !r.backed_up);
+        if !self.loading && !unsaved {
+            let encoded = schema::encode(
+                self.state.next_id(),
+                self.state.lists(),
+                self.state.entries(),
+            );
+            if let Err(err) = self.storage.store(KEY, &encoded) {
+                warn!("{}", err);
+                self.storage_error = Some(err.to_string());
             }
-        } else {
-            html! { <input type="hidden" /> }
         }
     }
-}
 
-#[derive(EnumIter, ToString, Clone, PartialEq, Serialize, Deserialize)]
-pub enum Filter {
-    All,
-    Active,
-    Completed,
-}
-
-impl<'a> Into<Href> for &'a Filter {
-    fn into(self) -> Href {
-        match *self {
-            Filter::All => "#/".into(),
-            Filter::Active => "#/active".into(),
-            Filter::Completed => "#/completed".into(),
+    fn view_toolbar(&self) -> Html {
+        let button = "rounded border-2 px-3 py-1 disabled:opacity-40";
+        html! {
+            <div class="flex justify-end gap-2 mt-4">
+                <button class=button title="Undo (Ctrl+Z)" disabled=!self.history.can_undo() onclick=self.link.callback(|_| Msg::Undo)>{ "Undo" }</button>
+                <button class=button title="Redo (Ctrl+Shift+Z)" disabled=!self.history.can_redo() onclick=self.link.callback(|_| Msg::Redo)>{ "Redo" }</button>
+            </div>
         }
     }
-}
 
-impl Filter {
-    fn fit(&self, entry: &Entry) -> bool {
-        match *self {
-            Filter::All => true,
-            Filter::Active => !entry.completed,
-            Filter::Completed => entry.completed,
+    /// Starts the event log and undo history over from the earlier state
+    /// the devtools show, as neither leads there: the log would replay to
+    /// the latest entries rather than the ones saved next.
+    #[cfg(feature = "devtools")]
+    fn continue_from_shown(&mut self) {
+        self.log = EventLog::new(self.state.to_persisted());
+        self.history = History::default();
+        self.history_dirty = true;
+        if let Some(idb) = &mut self.idb {
+            idb.forget_log();
         }
     }
-}
 
-impl State {
-    fn total(&self) -> usize {
-        self.entries.len()
+    #[cfg(feature = "devtools")]
+    fn state_json(&self) -> serde_json::Value {
+        serde_json::to_value(&self.state).expect("state is always serializable")
     }
 
-    fn total_completed(&self) -> usize {
-        self.entries
-            .iter()
-            .filter(|e| Filter::Completed.fit(e))
-            .count()
+    #[cfg(feature = "devtools")]
+    fn view_devtools(&self) -> Html {
+        self.devtools.view(&self.link.callback(Msg::Devtools))
     }
 
-    fn is_all_completed(&self) -> bool {
-        let mut filtered_iter = self
-            .entries
-            .iter()
-            .filter(|e| self.filter.fit(e))
-            .peekable();
+    #[cfg(not(feature = "devtools"))]
+    fn view_devtools(&self) -> Html {
+        html! {}
+    }
 
-        if filtered_iter.peek().is_none() {
-            return false;
+    fn view_log(&self) -> Html {
+        let on_import = self.link.callback(|data: ChangeData| match data {
+            ChangeData::Files(files) => match files.get(0) {
+                Some(file) => Msg::ImportLog(file),
+                None => Msg::Nope,
+            },
+            _ => Msg::Nope,
+        });
+        html! {
+            <section class="flex flex-wrap items-center gap-3 text-sm text-slate-500 border-t-2 border-slate-200 pt-4 mt-8">
+                <span>{ format!("Event log: {} changes recorded", self.log.events.len()) }</span>
+                { if self.idb.is_none() {
+                    html! { <span>{ "(since this page was opened; only IndexedDB keeps it across reloads)" }</span> }
+                } else {
+                    html! {}
+                } }
+                <button class="underline" onclick=self.link.callback(|_| Msg::ExportLog)>{ "Export" }</button>
+                <label class="underline cursor-pointer">
+                    { "Import" }
+                    <input type="file" accept="application/json" class="hidden" onchange=on_import />
+                </label>
+                { for self.log_error.iter().map(|err| html! {
+                    <span class="text-red-600">{ format!("Couldn't import event log: {}", err) }</span>
+                }) }
+            </section>
         }
-
-        filtered_iter.all(|e| e.completed)
     }
 
-    fn toggle_all(&mut self, value: bool) {
-        for entry in self.entries.iter_mut() {
-            if self.filter.fit(entry) {
-                entry.completed = value;
-            }
+    fn view_storage_error(&self) -> Html {
+        let error = match &self.storage_error {
+            Some(error) => error,
+            None => return html! {},
+        };
+        html! {
+            <div class="flex items-center gap-3 rounded bg-red-100 border-2 border-red-300 p-4 my-4">
+                <p>{ format!("Your latest changes couldn't be saved ({}).", error) }</p>
+                <button class="rounded border-2 p-2 ml-auto" onclick=self.link.callback(|_| Msg::DismissStorageError)>{ "Dismiss" }</button>
+            </div>
         }
     }
 
-    fn clear_completed(&mut self) {
-        let entries = self
-            .entries
-            .drain(..)
-            .filter(|e| Filter::Active.fit(e))
-            .collect();
-        self.entries = entries;
+    fn view_storage_notice(&self) -> Html {
+        let preferred = match self.unavailable_backend {
+            Some(preferred) => preferred,
+            None => return html! {},
+        };
+        let fallback = self.storage.backend();
+        let consequence = if fallback == Backend::Memory {
+            " Your todos will be lost when you close this page."
+        } else {
+            ""
+        };
+        html! {
+            <p class="rounded bg-slate-200 text-sm p-2 my-4">
+                { format!("{} storage is unavailable, using {} storage instead.{}", preferred, fallback, consequence) }
+            </p>
+        }
     }
 
-    fn toggle(&mut self, idx: usize) {
-        let filter = self.filter.clone();
-        let mut entries = self
-            .entries
-            .iter_mut()
-            .filter(|e| filter.fit(e))
-            .collect::<Vec<_>>();
-        let entry = entries.get_mut(idx).unwrap();
-        entry.completed = !entry.completed;
+    fn view_recovery(&self) -> Html {
+        let recovery = match &self.recovery {
+            Some(recovery) => recovery,
+            None => return html! {},
+        };
+        let kept = if recovery.backed_up {
+            "the original data was kept as a backup."
+        } else {
+            "the original data couldn't be kept as a backup, so changes aren't saved until you download it or reset."
+        };
+        html! {
+            <div class="flex flex-col gap-3 rounded bg-amber-100 border-2 border-amber-300 p-4 my-4">
+                <p>
+                    { format!("Your saved todos couldn't be read ({}). ", recovery.error) }
+                    { format!("{} of them could be recovered; {}", recovery.salvaged, kept) }
+                </p>
+                <div class="flex gap-3">
+                    <button class="rounded bg-amber-300 hover:bg-amber-500 p-2" onclick=self.link.callback(|_| Msg::DownloadBackup)>{ "Download backup" }</button>
+                    <button class="rounded bg-red-300 hover:bg-red-500 hover:text-white transition-colors p-2" onclick=self.link.callback(|_| Msg::ResetStorage)>{ "Reset" }</button>
+                    { if recovery.backed_up {
+                        html! { <button class="rounded border-2 p-2 ml-auto" onclick=self.link.callback(|_| Msg::DismissRecovery)>{ "Dismiss" }</button> }
+                    } else {
+                        html! {}
+                    } }
+                </div>
+            </div>
+        }
     }
+}
 
-    fn toggle_edit(&mut self, idx: usize) {
-        let filter = self.filter.clone();
-        let mut entries = self
-            .entries
-            .iter_mut()
-            .filter(|e| filter.fit(e))
-            .collect::<Vec<_>>();
-        let entry = entries.get_mut(idx).unwrap();
-        entry.editing = !entry.editing;
+fn local_now() -> Now {
+    let now = js_sys::Date::new_0();
+    Now {
+        date: Date {
+            year: now.get_full_year() as i32,
+            month: now.get_month() + 1,
+            day: now.get_date(),
+        },
+        time: Time {
+            hour: now.get_hours(),
+            minute: now.get_minutes(),
+        },
+        timestamp: now.get_time(),
     }
+}
 
-    fn complete_edit(&mut self, idx: usize, val: String) {
-        let filter = self.filter.clone();
-        let mut entries = self
-            .entries
-            .iter_mut()
-            .filter(|e| filter.fit(e))
-            .collect::<Vec<_>>();
-        let entry = entries.get_mut(idx).unwrap();
-        entry.description = val;
-        entry.editing = !entry.editing;
-    }
+/// Has the browser download `contents` as a JSON file called `name`.
+fn download(name: &str, contents: &str) -> Option<()> {
+    let link = web_sys::window()?.document()?.create_element("a").ok()?;
+    let href = format!(
+        "data:application/json;charset=utf-8,{}",
+        js_sys::encode_uri_component(contents)
+    );
+    link.set_attribute("href", &href).ok()?;
+    link.set_attribute("download", name).ok()?;
+    link.dyn_into::<HtmlElement>().ok()?.click();
+    Some(())
+}
 
-    fn remove(&mut self, idx: usize) {
-        let idx = {
-            let filter = self.filter.clone();
-            let entries = self
-                .entries
-                .iter()
-                .enumerate()
-                .filter(|&(_, e)| filter.fit(e))
-                .collect::<Vec<_>>();
-            let &(idx, _) = entries.get(idx).unwrap();
-            idx
-        };
-        self.entries.remove(idx);
-    }
+fn entry_element(id: EntryId) -> Option<Element> {
+    web_sys::window()?
+        .document()?
+        .get_element_by_id(&entry_element_id(id))
 }
@@ -0,0 +1,198 @@
+//! Time-travel debugging panel, compiled in with the `devtools` feature.
+//!
+//! Every message handled by `App::update` is recorded as a [`Frame`] along
+//! with the serialized `State` before and after it. The panel lists the
+//! frames, shows what each one changed, and can jump the app back to any
+//! earlier state and step forward through the recorded ones again.
+
+use serde_json::Value;
+use yew::prelude::*;
+
+/// How many frames are kept.
+const LIMIT: usize = 500;
+/// Longest message description shown in the list.
+const LABEL_LENGTH: usize = 80;
+
+pub struct Frame {
+    pub msg: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Debug)]
+pub enum Command {
+    Toggle,
+    Jump(usize),
+    Back,
+    Forward,
+    Resume,
+}
+
+#[derive(Default)]
+pub struct Devtools {
+    frames: Vec<Frame>,
+    /// The frame whose state is shown, if not the latest one.
+    cursor: Option<usize>,
+    open: bool,
+}
+
+impl Devtools {
+    /// Records a handled message. Recording while an earlier frame is shown
+    /// discards the frames after it, as the app has moved on from there.
+    pub fn record(&mut self, msg: String, before: Value, after: Value) {
+        if let Some(cursor) = self.cursor.take() {
+            self.frames.truncate(cursor + 1);
+        }
+        let msg = match msg.char_indices().nth(LABEL_LENGTH) {
+            Some((end, _)) => format!("{}…", &msg[..end]),
+            None => msg,
+        };
+        self.frames.push(Frame { msg, before, after });
+        if self.frames.len() > LIMIT {
+            self.frames.remove(0);
+        }
+    }
+
+    /// Whether an earlier frame than the latest one is shown.
+    pub fn shows_past(&self) -> bool {
+        self.cursor.is_some()
+    }
+
+    /// Handles a panel command, returning the state to show if it changed.
+    pub fn handle(&mut self, command: Command) -> Option<&Value> {
+        let last = self.frames.len().checked_sub(1)?;
+        let current = self.cursor.unwrap_or(last);
+        let target = match command {
+            Command::Toggle => {
+                self.open = !self.open;
+                return None;
+            }
+            Command::Jump(idx) => idx.min(last),
+            Command::Back => current.saturating_sub(1),
+            Command::Forward => (current + 1).min(last),
+            Command::Resume => last,
+        };
+        self.cursor = Some(target).filter(|&idx| idx != last);
+        Some(&self.frames[target].after)
+    }
+
+    pub fn view(&self, on_command: &Callback<Command>) -> Html {
+        let toggle = on_command.reform(|_| Command::Toggle);
+        if !self.open {
+            return html! {
+                <button class="fixed bottom-4 right-4 rounded bg-slate-800 text-white text-sm p-2" onclick=toggle>{ "Devtools" }</button>
+            };
+        }
+        let selected = self.cursor.or_else(|| self.frames.len().checked_sub(1));
+        let button = "rounded border-2 border-slate-500 px-2";
+        html! {
+            <aside class="fixed bottom-0 right-0 w-1/3 h-1/2 flex flex-col bg-slate-800 text-slate-100 text-xs font-mono">
+                <header class="flex gap-2 items-center p-2 border-b border-slate-600">
+                    <strong class="mr-auto">{ format!("{} messages", self.frames.len()) }</strong>
+                    <button class=button onclick=on_command.reform(|_| Command::Back)>{ "◀" }</button>
+                    <button class=button onclick=on_command.reform(|_| Command::Forward)>{ "▶" }</button>
+                    <button class=button disabled=self.cursor.is_none() onclick=on_command.reform(|_| Command::Resume)>{ "Resume" }</button>
+                    <button class=button onclick=toggle>{ "✕" }</button>
+                </header>
+                <ol class="flex-1 overflow-y-auto">
+                    { for self.frames.iter().enumerate().map(|(idx, frame)| {
+                        let class = if Some(idx) == selected { "px-2 bg-slate-600" } else { "px-2 hover:bg-slate-700" };
+                        html! {
+                            <li class=class onclick=on_command.reform(move |_| Command::Jump(idx))>
+                                { format!("{:>3} {}", idx, frame.msg) }
+                            </li>
+                        }
+                    }) }
+                </ol>
+                <ul class="h-1/3 overflow-y-auto p-2 border-t border-slate-600">
+                    { for selected
+                        .map(|idx| &self.frames[idx])
+                        .map(|frame| diff(&frame.before, &frame.after))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|line| html! { <li>{ line }</li> }) }
+                </ul>
+            </aside>
+        }
+    }
+}
+
+/// Lists the differences between two JSON values, one `path: old → new`
+/// line per changed leaf. Arrays of objects with an `id` are matched by id
+/// rather than by position, so removing an entry shows up as one change.
+pub fn diff(before: &Value, after: &Value) -> Vec<String> {
+    let mut changes = Vec::new();
+    diff_at("", before, after, &mut changes);
+    changes
+}
+
+fn diff_at(path: &str, before: &Value, after: &Value, changes: &mut Vec<String>) {
+    match (before, after) {
+        (Value::Object(before), Value::Object(after)) => {
+            let mut keys: Vec<&String> = before.keys().chain(after.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                diff_optional(&join(path, key), before.get(key), after.get(key), changes);
+            }
+        }
+        (Value::Array(before), Value::Array(after)) if has_ids(before) && has_ids(after) => {
+            let mut ids: Vec<&Value> = Vec::new();
+            for item in before.iter().chain(after) {
+                if !ids.contains(&&item["id"]) {
+                    ids.push(&item["id"]);
+                }
+            }
+            for id in ids {
+                diff_optional(
+                    &format!("{}[id={}]", path, id),
+                    find_by_id(before, id),
+                    find_by_id(after, id),
+                    changes,
+                );
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            for idx in 0..before.len().max(after.len()) {
+                diff_optional(
+                    &format!("{}[{}]", path, idx),
+                    before.get(idx),
+                    after.get(idx),
+                    changes,
+                );
+            }
+        }
+        _ if before == after => {}
+        _ => changes.push(format!("{}: {} → {}", path, before, after)),
+    }
+}
+
+fn diff_optional(
+    path: &str,
+    before: Option<&Value>,
+    after: Option<&Value>,
+    changes: &mut Vec<String>,
+) {
+    match (before, after) {
+        (Some(before), Some(after)) => diff_at(path, before, after, changes),
+        (Some(before), None) => changes.push(format!("{}: {} → (none)", path, before)),
+        (None, Some(after)) => changes.push(format!("{}: (none) → {}", path, after)),
+        (None, None) => {}
+    }
+}
+
+fn find_by_id<'a>(items: &'a [Value], id: &Value) -> Option<&'a Value> {
+    items.iter().find(|item| &item["id"] == id)
+}
+
+fn has_ids(items: &[Value]) -> bool {
+    items.iter().all(|item| item.get("id").is_some())
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
@@ -0,0 +1,236 @@
+//! Due dates of entries and how urgent they are, and how long ago other
+//! times were.
+//!
+//! Dates and times are wall-clock values in the user's local time zone, in
+//! the `YYYY-MM-DD` and `HH:MM` formats that date and time inputs use. They
+//! are compared against a [`Now`] passed in by the caller, so nothing in
+//! here reads the clock.
+
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Time {
+    pub hour: u32,
+    pub minute: u32,
+}
+
+/// When an entry is due: a day, optionally with a time on that day.
+/// Stored as `2024-05-01` or `2024-05-01T17:30`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Due {
+    pub date: Date,
+    pub time: Option<Time>,
+}
+
+/// The current local date and time.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Now {
+    pub date: Date,
+    pub time: Time,
+    /// The same moment in milliseconds since the Unix epoch, as entry
+    /// timestamps are kept.
+    pub timestamp: f64,
+}
+
+impl Now {
+    /// How long before now `timestamp` was, roughly, like "5m ago". Times
+    /// in the future, from a clock that was off, are "just now".
+    pub fn ago(&self, timestamp: f64) -> String {
+        const UNITS: [(f64, &str); 5] = [
+            (365.0 * 24.0 * 60.0, "y"),
+            (7.0 * 24.0 * 60.0, "w"),
+            (24.0 * 60.0, "d"),
+            (60.0, "h"),
+            (1.0, "m"),
+        ];
+        let minutes = (self.timestamp - timestamp) / 60_000.0;
+        UNITS
+            .iter()
+            .find(|(length, _)| minutes >= *length)
+            .map(|(length, unit)| format!("{}{} ago", (minutes / length).floor(), unit))
+            .unwrap_or_else(|| "just now".into())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Urgency {
+    /// The due date or time has passed.
+    Overdue,
+    /// Due later today.
+    Today,
+    /// Due on a later day.
+    Upcoming,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParseError(String);
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid date or time {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl Due {
+    pub fn urgency(&self, now: &Now) -> Urgency {
+        if self.date < now.date {
+            Urgency::Overdue
+        } else if self.date > now.date {
+            Urgency::Upcoming
+        } else if self.time.is_some_and(|time| time < now.time) {
+            Urgency::Overdue
+        } else {
+            Urgency::Today
+        }
+    }
+}
+
+impl Date {
+    /// The date `days` days later.
+    pub fn add_days(self, days: u32) -> Date {
+        let mut date = self;
+        for _ in 0..days {
+            date.day += 1;
+            if date.day > date.days_in_month() {
+                date.day = 1;
+                date.month += 1;
+                if date.month > 12 {
+                    date.month = 1;
+                    date.year += 1;
+                }
+            }
+        }
+        date
+    }
+
+    /// The day of the week, from 0 for Monday to 6 for Sunday.
+    pub fn weekday(&self) -> u32 {
+        // Sakamoto's method, which counts from Sunday.
+        const OFFSETS: [i32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+        let year = if self.month < 3 {
+            self.year - 1
+        } else {
+            self.year
+        };
+        let sunday_based = (year + year / 4 - year / 100
+            + year / 400
+            + OFFSETS[self.month as usize - 1]
+            + self.day as i32)
+            .rem_euclid(7);
+        (sunday_based as u32 + 6) % 7
+    }
+
+    fn days_in_month(&self) -> u32 {
+        match self.month {
+            2 if self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+}
+
+impl FromStr for Date {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError(s.to_string());
+        let mut parts = s.splitn(3, '-');
+        let mut next = || parts.next().ok_or_else(invalid);
+        let (year, month, day) = (next()?, next()?, next()?);
+        let date = Date {
+            year: year.parse().map_err(|_| invalid())?,
+            month: month.parse().map_err(|_| invalid())?,
+            day: day.parse().map_err(|_| invalid())?,
+        };
+        if !(1..=12).contains(&date.month) || !(1..=date.days_in_month()).contains(&date.day) {
+            return Err(invalid());
+        }
+        Ok(date)
+    }
+}
+
+impl FromStr for Time {
+    type Err = ParseError;
+
+    /// Parses `HH:MM`, ignoring any seconds.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseError(s.to_string());
+        let mut parts = s.split(':');
+        let hour = parts
+            .next()
+            .and_then(|h| h.parse().ok())
+            .ok_or_else(invalid)?;
+        let minute = parts
+            .next()
+            .and_then(|m| m.parse().ok())
+            .ok_or_else(invalid)?;
+        if hour > 23 || minute > 59 {
+            return Err(invalid());
+        }
+        Ok(Time { hour, minute })
+    }
+}
+
+impl FromStr for Due {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (date, time) = match s.split_once('T') {
+            Some((date, time)) => (date, Some(time.parse()?)),
+            None => (s, None),
+        };
+        Ok(Due {
+            date: date.parse()?,
+            time,
+        })
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+impl fmt::Display for Due {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.date)?;
+        if let Some(time) = self.time {
+            write!(f, "T{}", time)?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for Due {
+    type Error = ParseError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Due> for String {
+    fn from(due: Due) -> String {
+        due.to_string()
+    }
+}
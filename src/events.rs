@@ -0,0 +1,243 @@
+//! Log of every change made to the entries, recorded as serializable
+//! actions with a timestamp.
+//!
+//! `State` only changes its entries through [`State::apply`], so replaying
+//! the log on top of its snapshot reproduces the entries exactly. Changes to
+//! the UI session (filter, draft, edit buffer) are not entry data and are
+//! not logged. Actions record their effect rather than what caused it, like
+//! the ids [`Action::SetCompleted`] applies to instead of the filter that
+//! selected them, so that replaying them doesn't depend on the time.
+
+use crate::due::Due;
+use crate::model::{Entry, EntryId, Filter, List, ListId, Priority, State};
+use crate::schema::{self, Persisted, SchemaError};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Where the whole log used to be rewritten on every change. It is now only
+/// kept in IndexedDB, or for the session, so this is cleared on start.
+pub const LOG_KEY: &str = "yew.todomvc.log";
+
+/// Once the log grows past this many events, the older half is folded into
+/// the snapshot.
+const COMPACT_AFTER: usize = 500;
+
+/// The schema version of snapshots from before they were stored in a
+/// versioned envelope, when the log was introduced. Later fields are all
+/// added by migrations that keep values already there, so newer bare
+/// snapshots migrate from it just as well.
+const UNVERSIONED: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    Add {
+        id: EntryId,
+        description: String,
+        /// Missing from entries added before there were several lists,
+        /// which all went to the default one.
+        #[serde(default)]
+        list: ListId,
+    },
+    Edit {
+        id: EntryId,
+        description: String,
+    },
+    Remove {
+        id: EntryId,
+    },
+    Toggle {
+        id: EntryId,
+    },
+    /// Sets all entries under `filter` at once. Only written by earlier
+    /// versions, which had no filters that depend on the time.
+    ToggleAll {
+        filter: Filter,
+        completed: bool,
+    },
+    SetCompleted {
+        ids: Vec<EntryId>,
+        completed: bool,
+    },
+    SetDue {
+        id: EntryId,
+        due: Option<Due>,
+    },
+    SetPriority {
+        id: EntryId,
+        priority: Priority,
+    },
+    AddTag {
+        id: EntryId,
+        tag: String,
+    },
+    RemoveTag {
+        id: EntryId,
+        tag: String,
+    },
+    SetContext {
+        id: EntryId,
+        context: Option<String>,
+    },
+    AddSubtask {
+        id: EntryId,
+        subtask: EntryId,
+        description: String,
+    },
+    ToggleSubtask {
+        id: EntryId,
+        subtask: EntryId,
+    },
+    RemoveSubtask {
+        id: EntryId,
+        subtask: EntryId,
+    },
+    SetAutoComplete {
+        id: EntryId,
+        auto_complete: bool,
+    },
+    AddList {
+        id: ListId,
+        name: String,
+    },
+    RenameList {
+        id: ListId,
+        name: String,
+    },
+    /// Removes a list along with its entries, unless it is the last one.
+    RemoveList {
+        id: ListId,
+    },
+    MoveEntry {
+        id: EntryId,
+        list: ListId,
+    },
+    /// Moves entry `id` right before `target` in the list order, or right
+    /// after it if `after` is set.
+    Reorder {
+        id: EntryId,
+        target: EntryId,
+        after: bool,
+    },
+    /// Clears `list`, or every list if it is missing, as it is in logs
+    /// written before there were several lists.
+    ClearCompleted {
+        list: Option<ListId>,
+    },
+    /// Replaces all entries and lists at once, as undo, redo and resetting
+    /// do.
+    Replace {
+        entries: Vec<Entry>,
+        lists: Vec<List>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    /// Milliseconds since the Unix epoch.
+    pub at: f64,
+    pub action: Action,
+}
+
+#[derive(Debug, Default)]
+pub struct EventLog {
+    /// The entries before the first event.
+    pub snapshot: Persisted,
+    pub events: Vec<Event>,
+    /// How many events were folded into the snapshot since the log was
+    /// started, so each event keeps its number as the log is compacted.
+    /// Exported logs start over at zero.
+    pub folded: u64,
+}
+
+impl EventLog {
+    /// Starts an empty log from the given entries.
+    pub fn new(snapshot: Persisted) -> Self {
+        EventLog {
+            snapshot,
+            events: Vec::new(),
+            folded: 0,
+        }
+    }
+
+    /// Parses a log written by any version, migrating the entries in its
+    /// snapshot and in [`Action::Replace`] events like stored entries.
+    pub fn decode(raw: &str) -> Result<Self, SchemaError> {
+        EventLog::decode_value(serde_json::from_str(raw)?)
+    }
+
+    /// Like [`EventLog::decode`], for a log that was already parsed.
+    pub fn decode_value(mut log: Value) -> Result<Self, SchemaError> {
+        let snapshot = log
+            .get_mut("snapshot")
+            .map(Value::take)
+            .ok_or(SchemaError::Malformed("missing snapshot"))?;
+        let snapshot = match snapshot.get("version") {
+            Some(_) => snapshot,
+            None => json!({ "version": UNVERSIONED, "data": snapshot }),
+        };
+        // Events were written alongside the snapshot, in the same version.
+        let version = snapshot["version"]
+            .as_u64()
+            .ok_or(SchemaError::Malformed("missing version"))? as u32;
+        let mut events = log
+            .get_mut("events")
+            .map(Value::take)
+            .ok_or(SchemaError::Malformed("missing events"))?;
+        for action in events
+            .as_array_mut()
+            .into_iter()
+            .flatten()
+            .map(|event| &mut event["action"])
+            .filter(|action| action["type"] == "replace")
+        {
+            // Lists were only replaced along with entries once there were
+            // several of them.
+            let lists = action.get_mut("lists").map(Value::take);
+            let replaced = schema::migrate_parts(version, action["entries"].take(), lists)?;
+            action["entries"] = serde_json::to_value(replaced.entries)?;
+            action["lists"] = serde_json::to_value(replaced.lists)?;
+        }
+        Ok(EventLog {
+            snapshot: schema::decode_value(snapshot)?,
+            events: serde_json::from_value(events)?,
+            folded: 0,
+        })
+    }
+
+    /// Serializes the log with its snapshot in a versioned envelope, as
+    /// entries are stored.
+    pub fn encode(&self) -> String {
+        let encoded = json!({
+            "snapshot": schema::encode_value(
+                self.snapshot.next_id,
+                &self.snapshot.lists,
+                &self.snapshot.entries,
+            ),
+            "events": self.events,
+        });
+        encoded.to_string()
+    }
+
+    pub fn record(&mut self, at: f64, action: Action) {
+        self.events.push(Event { at, action });
+        if self.events.len() > COMPACT_AFTER {
+            let folded = self.events.len() / 2;
+            let mut state = State::new(self.snapshot.clone());
+            for event in self.events.drain(..folded) {
+                state.apply(event.at, &event.action);
+            }
+            self.snapshot = state.to_persisted();
+            self.folded += folded as u64;
+        }
+    }
+
+    /// Rebuilds the state by applying every event to the snapshot.
+    pub fn replay(&self) -> State {
+        let mut state = State::new(self.snapshot.clone());
+        for event in &self.events {
+            state.apply(event.at, &event.action);
+        }
+        state
+    }
+}
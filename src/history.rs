@@ -0,0 +1,267 @@
+//! Bounded undo/redo history.
+//!
+//! Steps only hold what they change, as a [`Snapshot`] holds the whole
+//! state: undoing a change to one todo keeps that todo, not the list it is
+//! in. The history is stored in a versioned envelope, and the entries in its
+//! steps go through the same migrations as stored entries.
+
+use crate::model::{Entry, EntryId, List, State};
+use crate::schema::{self, SchemaError, CURRENT_VERSION};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub const HISTORY_KEY: &str = "yew.todomvc.history";
+
+/// How many steps can be undone.
+const LIMIT: usize = 50;
+
+/// A state that undo and redo go between.
+pub trait Undoable {
+    /// What turns one state into another.
+    type Step;
+
+    /// The step that turns `self` into `to`.
+    fn step_to(&self, to: &Self) -> Self::Step;
+
+    /// The state `step` turns `self` into.
+    fn apply(&self, step: &Self::Step) -> Self;
+}
+
+pub struct History<T: Undoable> {
+    undo: VecDeque<T::Step>,
+    redo: Vec<T::Step>,
+    /// Group of the last recorded step, see [`History::record`].
+    group: Option<&'static str>,
+}
+
+impl<T: Undoable> Default for History<T> {
+    fn default() -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            group: None,
+        }
+    }
+}
+
+impl<T: Undoable> History<T> {
+    /// Records the change from `before` to `after` as an undo step and
+    /// returns whether a new step was added.
+    ///
+    /// Consecutive changes in the same `group`, such as typing one character
+    /// at a time, are coalesced into the first one's step, so a single undo
+    /// reverts all of them.
+    pub fn record(&mut self, before: &T, after: &T, group: Option<&'static str>) -> bool {
+        if group.is_some() && group == self.group && self.redo.is_empty() {
+            if let Some(step) = self.undo.back_mut() {
+                let start = before.apply(step);
+                *step = after.step_to(&start);
+                return false;
+            }
+        }
+        self.group = group;
+        self.redo.clear();
+        self.undo.push_back(after.step_to(before));
+        if self.undo.len() > LIMIT {
+            self.undo.pop_front();
+        }
+        true
+    }
+
+    /// Returns the state to go back to from `current`, remembering the way
+    /// back to it for redo.
+    pub fn undo(&mut self, current: &T) -> Option<T> {
+        let step = self.undo.pop_back()?;
+        let previous = current.apply(&step);
+        self.redo.push(previous.step_to(current));
+        self.group = None;
+        Some(previous)
+    }
+
+    /// Returns the state to go forward to from `current`, remembering the
+    /// way back to it for undo.
+    pub fn redo(&mut self, current: &T) -> Option<T> {
+        let step = self.redo.pop()?;
+        let next = current.apply(&step);
+        self.undo.push_back(next.step_to(current));
+        self.group = None;
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+/// The part of `State` that undo and redo restore.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snapshot {
+    pub entries: Vec<Entry>,
+    pub lists: Vec<List>,
+    /// The new todo's draft.
+    pub value: String,
+}
+
+impl Snapshot {
+    pub fn of(state: &State) -> Self {
+        Snapshot {
+            entries: state.entries().to_vec(),
+            lists: state.lists().to_vec(),
+            value: state.value.clone(),
+        }
+    }
+}
+
+/// What differs from one snapshot to another.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Changes {
+    /// Entries that were added, changed or moved, as they are afterwards.
+    entries: Vec<Entry>,
+    /// Where each of `entries` goes in the list, once all of them are in.
+    positions: Vec<usize>,
+    removed: Vec<EntryId>,
+    lists: Option<Vec<List>>,
+    value: Option<String>,
+}
+
+impl Undoable for Snapshot {
+    type Step = Changes;
+
+    fn step_to(&self, to: &Snapshot) -> Changes {
+        let positions: HashMap<EntryId, usize> = to
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (e.id, i))
+            .collect();
+        let removed = self
+            .entries
+            .iter()
+            .map(|e| e.id)
+            .filter(|id| !positions.contains_key(id))
+            .collect();
+        // Of the entries that stay the same, the longest run that is still
+        // in order stays where it is, and the others are moved.
+        let same: Vec<usize> = self
+            .entries
+            .iter()
+            .filter_map(|e| {
+                positions
+                    .get(&e.id)
+                    .copied()
+                    .filter(|&i| to.entries[i] == *e)
+            })
+            .collect();
+        let kept: HashSet<usize> = longest_increasing(&same).into_iter().collect();
+        let (positions, entries) = to
+            .entries
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !kept.contains(i))
+            .map(|(i, entry)| (i, entry.clone()))
+            .unzip();
+        Changes {
+            entries,
+            positions,
+            removed,
+            lists: Some(&to.lists).filter(|&l| *l != self.lists).cloned(),
+            value: Some(&to.value).filter(|&v| *v != self.value).cloned(),
+        }
+    }
+
+    fn apply(&self, changes: &Changes) -> Snapshot {
+        let moved: HashSet<EntryId> = changes
+            .removed
+            .iter()
+            .copied()
+            .chain(changes.entries.iter().map(|e| e.id))
+            .collect();
+        let mut entries: Vec<Entry> = self
+            .entries
+            .iter()
+            .filter(|e| !moved.contains(&e.id))
+            .cloned()
+            .collect();
+        // Positions go up, so each one is counted with those before it in.
+        for (&position, entry) in changes.positions.iter().zip(&changes.entries) {
+            entries.insert(position.min(entries.len()), entry.clone());
+        }
+        Snapshot {
+            entries,
+            lists: changes.lists.clone().unwrap_or_else(|| self.lists.clone()),
+            value: changes.value.clone().unwrap_or_else(|| self.value.clone()),
+        }
+    }
+}
+
+impl History<Snapshot> {
+    /// Serializes the steps in an envelope tagged with the schema version
+    /// of their entries.
+    pub fn encode(&self) -> String {
+        let encoded = json!({
+            "version": CURRENT_VERSION,
+            "undo": self.undo,
+            "redo": self.redo,
+        });
+        encoded.to_string()
+    }
+
+    /// Parses a history written by any version, migrating the entries in
+    /// its steps like stored entries.
+    pub fn decode(raw: &str) -> Result<Self, SchemaError> {
+        let mut history: Value = serde_json::from_str(raw)?;
+        let version = history["version"]
+            .as_u64()
+            .ok_or(SchemaError::Malformed("missing version"))? as u32;
+        let mut steps = |key: &str| -> Result<Vec<Changes>, SchemaError> {
+            let mut steps = history[key].take();
+            for step in steps.as_array_mut().into_iter().flatten() {
+                let lists = step.get_mut("lists").map(Value::take);
+                let lists = lists.filter(|lists| !lists.is_null());
+                let changed_lists = lists.is_some();
+                let migrated = schema::migrate_parts(version, step["entries"].take(), lists)?;
+                step["entries"] = serde_json::to_value(migrated.entries)?;
+                if changed_lists {
+                    step["lists"] = serde_json::to_value(migrated.lists)?;
+                }
+            }
+            Ok(serde_json::from_value(steps)?)
+        };
+        Ok(History {
+            undo: steps("undo")?.into(),
+            redo: steps("redo")?,
+            group: None,
+        })
+    }
+}
+
+/// The values of a longest strictly increasing subsequence of `values`, in
+/// no particular order.
+fn longest_increasing(values: &[usize]) -> Vec<usize> {
+    // `tails[k]` is the index of the smallest value that ends an increasing
+    // run of length `k + 1`, and `previous[i]` the index before `i` in its
+    // run.
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; values.len()];
+    for (i, &value) in values.iter().enumerate() {
+        let k = tails.partition_point(|&t| values[t] < value);
+        previous[i] = k.checked_sub(1).map(|k| tails[k]);
+        if k == tails.len() {
+            tails.push(i);
+        } else {
+            tails[k] = i;
+        }
+    }
+    let mut run = Vec::with_capacity(tails.len());
+    let mut next = tails.last().copied();
+    while let Some(i) = next {
+        run.push(values[i]);
+        next = previous[i];
+    }
+    run
+}
@@ -1,6 +1,20 @@
 #![recursion_limit = "512"]
 
-mod app;
+pub mod app;
+#[cfg(feature = "devtools")]
+pub mod devtools;
+pub mod due;
+pub mod events;
+pub mod history;
+mod listener;
+pub mod model;
+pub mod quick_add;
+pub mod route;
+pub mod schema;
+pub mod session;
+pub mod storage;
+pub mod validation;
+pub mod view;
 
 use wasm_bindgen::prelude::*;
 
@@ -0,0 +1,855 @@
+//! The todo list itself: entries, filters and the operations on them.
+//!
+//! Nothing in here depends on yew or the browser, so it builds and is
+//! tested on the host with a plain `cargo test`. `App` is only an adapter
+//! that turns DOM events into calls on [`State`] and renders it.
+
+use crate::due::{Due, Now, Urgency};
+use crate::events::Action;
+use crate::schema::Persisted;
+use crate::session::Session;
+use crate::validation::Invalid;
+use serde_derive::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use strum_macros::{Display, EnumIter, EnumString};
+
+/// Stable identifier of an `Entry`, independent of its position in the list
+/// or in the currently filtered view.
+pub type EntryId = u64;
+
+/// Identifier of a [`List`]. New ones come from the same sequence as entry
+/// ids; the list that existed before there were several has
+/// [`DEFAULT_LIST`].
+pub type ListId = u64;
+
+pub const DEFAULT_LIST: ListId = 0;
+const DEFAULT_LIST_NAME: &str = "Todos";
+
+/// The entries along with the UI state that decides how they are shown.
+///
+/// Entries only change through [`State::apply`], which keeps the event log
+/// complete; the other fields are plain UI state the app sets directly.
+#[derive(Serialize, Deserialize)]
+pub struct State {
+    entries: Vec<Entry>,
+    /// Never empty, and every entry is in one of them.
+    lists: Vec<List>,
+    /// The list that is shown.
+    pub list: ListId,
+    /// The list being renamed, if any.
+    pub renaming: Option<ListId>,
+    pub filter: Filter,
+    /// The new todo being typed.
+    pub value: String,
+    /// The entry being edited, if any.
+    pub editing: Option<EntryId>,
+    pub edit_value: String,
+    /// Why the new todo was last rejected, until it is changed.
+    #[serde(skip)]
+    pub value_error: Option<Invalid>,
+    /// Why the edit was last rejected, until it is changed.
+    #[serde(skip)]
+    pub edit_error: Option<Invalid>,
+    /// How each list is sorted. Lists missing here are in list order.
+    pub sorts: BTreeMap<ListId, SortOrder>,
+    /// Only entries with this priority are shown, if set.
+    pub priority_filter: Option<Priority>,
+    /// Only entries with this tag are shown, if set.
+    pub tag_filter: Option<String>,
+    /// Entries whose subtasks are hidden.
+    pub collapsed: BTreeSet<EntryId>,
+    /// The entry being dragged to a new position, if any.
+    #[serde(skip)]
+    pub dragging: Option<EntryId>,
+    /// The time due dates are compared against, kept current by the app.
+    #[serde(skip)]
+    pub now: Now,
+    next_id: EntryId,
+}
+
+/// A named list of entries, like a project.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct List {
+    pub id: ListId,
+    pub name: String,
+}
+
+impl Default for List {
+    fn default() -> Self {
+        List {
+            id: DEFAULT_LIST,
+            name: DEFAULT_LIST_NAME.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: EntryId,
+    pub list: ListId,
+    pub description: String,
+    pub completed: bool,
+    pub due: Option<Due>,
+    pub priority: Priority,
+    pub tags: Vec<String>,
+    /// Where or with what the entry can be done, like `home` or `phone`.
+    pub context: Option<String>,
+    pub subtasks: Vec<Subtask>,
+    /// Whether the entry is completed once all its subtasks are, and
+    /// reopened when one of them is.
+    pub auto_complete: bool,
+    /// When the entry was added, in milliseconds since the Unix epoch like
+    /// the other timestamps. Unknown for entries added before this was
+    /// kept.
+    pub created_at: Option<f64>,
+    /// When the entry was added or last changed. Unknown for entries last
+    /// changed before this was kept.
+    pub updated_at: Option<f64>,
+    /// When the description was last edited, unlike `updated_at` which
+    /// any change sets. Unknown for entries edited before this was kept.
+    pub edited_at: Option<f64>,
+    /// When the entry was completed, while it is. Unknown for entries
+    /// completed before this was kept.
+    pub completed_at: Option<f64>,
+}
+
+/// A checklist item of an entry. Its id comes from the same sequence as
+/// entry ids, so it is unique across the list.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Subtask {
+    pub id: EntryId,
+    pub description: String,
+    pub completed: bool,
+}
+
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    Display,
+    EnumIter,
+    EnumString,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Default, EnumIter, Display, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Filter {
+    #[default]
+    All,
+    Active,
+    Completed,
+    Today,
+    Upcoming,
+    Overdue,
+}
+
+impl Filter {
+    /// Whether the entry is shown under this filter at the time `now`. The
+    /// due date filters only show open entries. Only the entry's own state
+    /// counts; its subtasks are shown along with it.
+    pub fn fit(&self, entry: &Entry, now: &Now) -> bool {
+        let urgency = || entry.due.map(|due| due.urgency(now));
+        match *self {
+            Filter::All => true,
+            Filter::Active => !entry.completed,
+            Filter::Completed => entry.completed,
+            Filter::Today => !entry.completed && urgency() == Some(Urgency::Today),
+            Filter::Upcoming => !entry.completed && urgency() == Some(Urgency::Upcoming),
+            Filter::Overdue => !entry.completed && urgency() == Some(Urgency::Overdue),
+        }
+    }
+}
+
+impl Entry {
+    /// How many subtasks are completed, out of how many, if there are any.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        if self.subtasks.is_empty() {
+            return None;
+        }
+        let done = self.subtasks.iter().filter(|s| s.completed).count();
+        Some((done, self.subtasks.len()))
+    }
+
+    /// Completing an entry completes its subtasks too, while reopening it
+    /// leaves them as they are. An entry that completes automatically
+    /// stops doing so when it is reopened with all its subtasks done, since
+    /// it would otherwise contradict them.
+    fn set_completed(&mut self, completed: bool) {
+        self.completed = completed;
+        if completed {
+            for subtask in self.subtasks.iter_mut() {
+                subtask.completed = true;
+            }
+        } else if self.subtasks.iter().all(|s| s.completed) {
+            self.auto_complete = false;
+        }
+    }
+
+    /// Follows the subtasks if the entry completes automatically.
+    fn update_auto_complete(&mut self) {
+        if self.auto_complete && !self.subtasks.is_empty() {
+            self.completed = self.subtasks.iter().all(|s| s.completed);
+        }
+    }
+}
+
+impl Priority {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Priority::None => "None",
+            Priority::Low => "Low",
+            Priority::Medium => "Medium",
+            Priority::High => "High",
+        }
+    }
+}
+
+/// What visible entries are ordered by. Each is described in ascending
+/// order.
+#[derive(
+    Clone, Copy, Debug, Default, Display, EnumIter, EnumString, PartialEq, Serialize, Deserialize,
+)]
+#[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum Sort {
+    /// The order they were added or dragged in.
+    #[default]
+    Manual,
+    /// Oldest first. Ids are handed out in the order entries are added, so
+    /// they tell.
+    Created,
+    /// Least recently changed first, then the ones last changed before
+    /// that was recorded.
+    Modified,
+    /// By description, ignoring case.
+    Alphabetical,
+    /// Open entries first.
+    Completion,
+    /// Soonest due first, then the ones without a due date.
+    Due,
+    /// Highest priority first.
+    Priority,
+}
+
+impl Sort {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Sort::Manual => "List order",
+            Sort::Created => "Created",
+            Sort::Modified => "Last modified",
+            Sort::Alphabetical => "Alphabetical",
+            Sort::Completion => "Completion",
+            Sort::Due => "Due date",
+            Sort::Priority => "Priority",
+        }
+    }
+}
+
+/// A sort and its direction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SortOrder {
+    pub by: Sort,
+    pub descending: bool,
+}
+
+impl From<Sort> for SortOrder {
+    fn from(by: Sort) -> Self {
+        SortOrder {
+            by,
+            descending: false,
+        }
+    }
+}
+
+impl SortOrder {
+    /// Compares two entries in this order. Entries without a value to sort
+    /// by, like a due date, go last in either direction.
+    fn compare(&self, a: &Entry, b: &Entry) -> Ordering {
+        let directed = |ordering: Ordering| {
+            if self.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        };
+        match self.by {
+            Sort::Manual => Ordering::Equal,
+            Sort::Created => directed(a.id.cmp(&b.id)),
+            Sort::Modified => {
+                present_first(a.updated_at, b.updated_at, |a, b| directed(a.total_cmp(&b)))
+            }
+            Sort::Alphabetical => directed(
+                a.description
+                    .to_lowercase()
+                    .cmp(&b.description.to_lowercase()),
+            ),
+            Sort::Completion => directed(a.completed.cmp(&b.completed)),
+            Sort::Due => present_first(a.due, b.due, |a, b| directed(a.cmp(&b))),
+            Sort::Priority => directed(b.priority.cmp(&a.priority)),
+        }
+    }
+}
+
+/// Compares with `compare` if both values are there, and puts missing ones
+/// last otherwise.
+fn present_first<T>(
+    a: Option<T>,
+    b: Option<T>,
+    compare: impl FnOnce(T, T) -> Ordering,
+) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => compare(a, b),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    }
+}
+
+impl State {
+    /// How many entries the shown list has.
+    pub fn total(&self) -> usize {
+        self.in_list(self.list).count()
+    }
+
+    /// How many entries of the shown list are completed.
+    pub fn total_completed(&self) -> usize {
+        self.in_list(self.list).filter(|e| e.completed).count()
+    }
+
+    /// The entries of a list, in list order.
+    pub fn in_list(&self, list: ListId) -> impl Iterator<Item = &Entry> {
+        self.entries.iter().filter(move |e| e.list == list)
+    }
+
+    /// Whether there are visible entries and all of them are completed.
+    pub fn is_all_completed(&self) -> bool {
+        let mut filtered_iter = self.entries.iter().filter(|e| self.shows(e)).peekable();
+
+        if filtered_iter.peek().is_none() {
+            return false;
+        }
+
+        filtered_iter.all(|e| e.completed)
+    }
+
+    fn toggle_all(&mut self, filter: &Filter, value: bool) {
+        let now = self.now;
+        for entry in self.entries.iter_mut() {
+            if filter.fit(entry, &now) {
+                entry.set_completed(value);
+            }
+        }
+    }
+
+    /// Removes completed entries from `list` or from every list. The
+    /// subtasks of open entries stay, done or not, as their progress.
+    fn clear_completed(&mut self, list: Option<ListId>) {
+        self.entries
+            .retain(|e| !(e.completed && list.is_none_or(|list| e.list == list)));
+    }
+
+    /// How the shown list is sorted.
+    pub fn sort(&self) -> SortOrder {
+        self.sorts.get(&self.list).copied().unwrap_or_default()
+    }
+
+    /// Sorts the shown list.
+    pub fn set_sort(&mut self, order: SortOrder) {
+        if order == SortOrder::default() {
+            self.sorts.remove(&self.list);
+        } else {
+            self.sorts.insert(self.list, order);
+        }
+    }
+
+    pub fn lists(&self) -> &[List] {
+        &self.lists
+    }
+
+    pub fn current_list(&self) -> &List {
+        self.lists
+            .iter()
+            .find(|l| l.id == self.list)
+            .unwrap_or(&self.lists[0])
+    }
+
+    /// The list with this name, ignoring case.
+    pub fn list_named(&self, name: &str) -> Option<&List> {
+        self.lists
+            .iter()
+            .find(|l| l.name.to_lowercase() == name.to_lowercase())
+    }
+
+    /// Restores what the `lists` field promises after the lists or entries
+    /// were replaced: there is at least one list, entries of lists that are
+    /// gone move to the first one, and so does the shown list.
+    fn fix_lists(&mut self) {
+        if self.lists.is_empty() {
+            self.lists.push(List::default());
+        }
+        let first = self.lists[0].id;
+        let exists = |lists: &[List], id: ListId| lists.iter().any(|l| l.id == id);
+        for entry in self.entries.iter_mut() {
+            if !exists(&self.lists, entry.list) {
+                entry.list = first;
+            }
+        }
+        if !exists(&self.lists, self.list) {
+            self.list = first;
+        }
+        if self.renaming.is_some_and(|id| !exists(&self.lists, id)) {
+            self.renaming = None;
+        }
+        let lists = &self.lists;
+        self.sorts.retain(|&id, _| exists(lists, id));
+    }
+
+    /// Starts from persisted entries, with the default filter and empty
+    /// inputs.
+    pub fn new(persisted: Persisted) -> Self {
+        let mut state = State {
+            entries: persisted.entries,
+            lists: persisted.lists,
+            list: DEFAULT_LIST,
+            renaming: None,
+            filter: Filter::All,
+            value: "".into(),
+            editing: None,
+            edit_value: "".into(),
+            value_error: None,
+            edit_error: None,
+            sorts: BTreeMap::new(),
+            priority_filter: None,
+            tag_filter: None,
+            collapsed: BTreeSet::new(),
+            dragging: None,
+            now: Now::default(),
+            next_id: persisted.next_id.max(1),
+        };
+        state.fix_lists();
+        state
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// The action that moves entry `id` past its visible neighbour, below
+    /// it or above it. Entries are only reordered while they are shown in
+    /// list order, where the move is visible.
+    pub fn move_past(&self, id: EntryId, below: bool) -> Option<Action> {
+        if self.sort().by != Sort::Manual {
+            return None;
+        }
+        let target = self.neighbour(id, below)?;
+        Some(self.reorder_action(id, target, below))
+    }
+
+    /// The action that moves entry `id` into the place of `target`, where
+    /// it was dropped, whichever way it was dragged.
+    pub fn drop_onto(&self, id: EntryId, target: EntryId) -> Option<Action> {
+        if self.sort().by != Sort::Manual || id == target {
+            return None;
+        }
+        Some(self.reorder_action(id, target, self.is_above(id, target)))
+    }
+
+    /// The action that completes the visible entries, or reopens them if
+    /// they are all completed.
+    pub fn toggle_visible(&self) -> Action {
+        Action::SetCompleted {
+            ids: self.visible().iter().map(|e| e.id).collect(),
+            completed: !self.is_all_completed(),
+        }
+    }
+
+    /// Moves `id` next to `target`, shown below it or above it. Shown in
+    /// reverse, an entry moved below another goes before it in the list.
+    fn reorder_action(&self, id: EntryId, target: EntryId, below: bool) -> Action {
+        Action::Reorder {
+            id,
+            target,
+            after: below != self.sort().descending,
+        }
+    }
+
+    /// The visible entry right before `id`, or right after it if `after`
+    /// is set. Entries hidden by the filters are skipped.
+    fn neighbour(&self, id: EntryId, after: bool) -> Option<EntryId> {
+        let visible = self.visible();
+        let idx = visible.iter().position(|e| e.id == id)?;
+        let neighbour = if after {
+            visible.get(idx + 1)
+        } else {
+            idx.checked_sub(1).and_then(|idx| visible.get(idx))
+        };
+        neighbour.map(|e| e.id)
+    }
+
+    /// Whether `id` comes before `other` among the visible entries.
+    fn is_above(&self, id: EntryId, other: EntryId) -> bool {
+        let visible = self.visible();
+        let position = |id| visible.iter().position(|e| e.id == id);
+        matches!((position(id), position(other)), (Some(a), Some(b)) if a < b)
+    }
+
+    /// The entries that pass the current filter, in the current order.
+    pub fn visible(&self) -> Vec<&Entry> {
+        let mut visible: Vec<&Entry> = self.entries.iter().filter(|e| self.shows(e)).collect();
+        let order = self.sort();
+        if order.by == Sort::Manual && order.descending {
+            visible.reverse();
+        }
+        // The sort is stable, so entries that compare equal stay in list
+        // order.
+        visible.sort_by(|a, b| order.compare(a, b));
+        visible
+    }
+
+    /// Whether the entry is in the shown list and passes the filter, the
+    /// priority and the tag filter.
+    fn shows(&self, entry: &Entry) -> bool {
+        entry.list == self.list
+            && self.filter.fit(entry, &self.now)
+            && self.priority_filter.is_none_or(|p| entry.priority == p)
+            && self
+                .tag_filter
+                .as_ref()
+                .is_none_or(|t| entry.tags.contains(t))
+    }
+
+    /// Every tag in use, sorted.
+    pub fn tags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = self
+            .entries
+            .iter()
+            .flat_map(|e| e.tags.iter().map(String::as_str))
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+        tags
+    }
+
+    /// The id the next added entry gets.
+    pub fn next_id(&self) -> EntryId {
+        self.next_id
+    }
+
+    pub fn to_persisted(&self) -> Persisted {
+        Persisted {
+            next_id: self.next_id,
+            lists: self.lists.clone(),
+            entries: self.entries.clone(),
+        }
+    }
+
+    /// The entries an action changes the contents of, as opposed to adding,
+    /// removing or moving them around.
+    fn edited_by(&self, action: &Action) -> Vec<EntryId> {
+        match action {
+            Action::Edit { id, .. }
+            | Action::Toggle { id }
+            | Action::SetDue { id, .. }
+            | Action::SetPriority { id, .. }
+            | Action::AddTag { id, .. }
+            | Action::RemoveTag { id, .. }
+            | Action::SetContext { id, .. }
+            | Action::AddSubtask { id, .. }
+            | Action::ToggleSubtask { id, .. }
+            | Action::RemoveSubtask { id, .. }
+            | Action::SetAutoComplete { id, .. }
+            | Action::MoveEntry { id, .. } => vec![*id],
+            Action::SetCompleted { ids, .. } => ids.clone(),
+            Action::ToggleAll { .. } => self.entries.iter().map(|e| e.id).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Applies a change to the entries that happened `at` milliseconds
+    /// since the Unix epoch. This is the only way entries change, so that
+    /// replaying the event log, which records when each change happened,
+    /// reproduces them.
+    pub fn apply(&mut self, at: f64, action: &Action) {
+        let before: Vec<Entry> = self
+            .edited_by(action)
+            .into_iter()
+            .filter_map(|id| self.entry(id).cloned())
+            .collect();
+        match action {
+            Action::Add {
+                id,
+                description,
+                list,
+            } => {
+                let list = if self.lists.iter().any(|l| l.id == *list) {
+                    *list
+                } else {
+                    self.lists[0].id
+                };
+                self.entries.push(Entry {
+                    id: *id,
+                    list,
+                    description: description.clone(),
+                    created_at: Some(at),
+                    updated_at: Some(at),
+                    ..Entry::default()
+                });
+                self.next_id = self.next_id.max(id + 1);
+            }
+            Action::Edit { id, description } => {
+                if let Some(entry) = self
+                    .entry_mut(*id)
+                    .filter(|e| e.description != *description)
+                {
+                    entry.description = description.clone();
+                    entry.edited_at = Some(at);
+                }
+            }
+            Action::Remove { id } => self.remove(*id),
+            Action::Toggle { id } => self.toggle(*id),
+            Action::ToggleAll { filter, completed } => self.toggle_all(filter, *completed),
+            Action::SetCompleted { ids, completed } => {
+                for entry in self.entries.iter_mut().filter(|e| ids.contains(&e.id)) {
+                    entry.set_completed(*completed);
+                }
+            }
+            Action::SetDue { id, due } => {
+                if let Some(entry) = self.entry_mut(*id) {
+                    entry.due = *due;
+                }
+            }
+            Action::SetPriority { id, priority } => {
+                if let Some(entry) = self.entry_mut(*id) {
+                    entry.priority = *priority;
+                }
+            }
+            Action::AddTag { id, tag } => {
+                if let Some(entry) = self.entry_mut(*id) {
+                    if !entry.tags.contains(tag) {
+                        entry.tags.push(tag.clone());
+                    }
+                }
+            }
+            Action::RemoveTag { id, tag } => {
+                if let Some(entry) = self.entry_mut(*id) {
+                    entry.tags.retain(|t| t != tag);
+                }
+            }
+            Action::SetContext { id, context } => {
+                if let Some(entry) = self.entry_mut(*id) {
+                    entry.context = context.clone();
+                }
+            }
+            Action::AddSubtask {
+                id,
+                subtask,
+                description,
+            } => {
+                if let Some(entry) = self.entry_mut(*id) {
+                    entry.subtasks.push(Subtask {
+                        id: *subtask,
+                        description: description.clone(),
+                        completed: false,
+                    });
+                    entry.update_auto_complete();
+                }
+                self.next_id = self.next_id.max(subtask + 1);
+            }
+            Action::ToggleSubtask { id, subtask } => {
+                if let Some(entry) = self.entry_mut(*id) {
+                    if let Some(subtask) = entry.subtasks.iter_mut().find(|s| s.id == *subtask) {
+                        subtask.completed = !subtask.completed;
+                    }
+                    entry.update_auto_complete();
+                }
+            }
+            Action::RemoveSubtask { id, subtask } => {
+                if let Some(entry) = self.entry_mut(*id) {
+                    entry.subtasks.retain(|s| s.id != *subtask);
+                    entry.update_auto_complete();
+                }
+            }
+            Action::SetAutoComplete { id, auto_complete } => {
+                if let Some(entry) = self.entry_mut(*id) {
+                    entry.auto_complete = *auto_complete;
+                    entry.update_auto_complete();
+                }
+            }
+            Action::AddList { id, name } => {
+                self.lists.push(List {
+                    id: *id,
+                    name: name.clone(),
+                });
+                self.next_id = self.next_id.max(id + 1);
+            }
+            Action::RenameList { id, name } => {
+                if let Some(list) = self.lists.iter_mut().find(|l| l.id == *id) {
+                    list.name = name.clone();
+                }
+            }
+            Action::RemoveList { id } => {
+                // The last list stays.
+                if self.lists.len() > 1 {
+                    self.lists.retain(|l| l.id != *id);
+                    self.entries.retain(|e| e.list != *id);
+                    self.fix_lists();
+                }
+            }
+            Action::MoveEntry { id, list } => {
+                let exists = self.lists.iter().any(|l| l.id == *list);
+                if let Some(entry) = self.entry_mut(*id).filter(|_| exists) {
+                    entry.list = *list;
+                }
+            }
+            Action::Reorder { id, target, after } => self.reorder(*id, *target, *after),
+            Action::ClearCompleted { list } => self.clear_completed(*list),
+            Action::Replace { entries, lists } => {
+                self.entries = entries.clone();
+                self.lists = lists.clone();
+                self.fix_lists();
+                let max_id = self
+                    .entries
+                    .iter()
+                    .flat_map(|e| std::iter::once(e.id).chain(e.subtasks.iter().map(|s| s.id)))
+                    .chain(self.lists.iter().map(|l| l.id))
+                    .max()
+                    .unwrap_or(0);
+                self.next_id = self.next_id.max(max_id + 1);
+            }
+        }
+        // Only actual changes count, not say adding a tag that was there.
+        for before in before {
+            if let Some(entry) = self.entry_mut(before.id).filter(|e| **e != before) {
+                entry.updated_at = Some(at);
+                if !entry.completed {
+                    entry.completed_at = None;
+                } else if !before.completed {
+                    entry.completed_at = Some(at);
+                }
+            }
+        }
+        if let Some(id) = self.editing {
+            if self.entry(id).is_none() {
+                self.editing = None;
+                self.edit_error = None;
+            }
+        }
+    }
+
+    /// Replaces the entries with ones loaded from elsewhere, without
+    /// recording an action, as when a load finishes or a log is imported.
+    pub fn load(&mut self, persisted: Persisted) {
+        // Replacing doesn't count as changing any entry, so the time is
+        // never used.
+        self.apply(
+            0.0,
+            &Action::Replace {
+                entries: persisted.entries,
+                lists: persisted.lists,
+            },
+        );
+        self.next_id = self.next_id.max(persisted.next_id);
+    }
+
+    /// Hands out an id for a new entry, subtask or list.
+    pub fn allocate_id(&mut self) -> EntryId {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    pub fn entry(&self, id: EntryId) -> Option<&Entry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+
+    fn entry_mut(&mut self, id: EntryId) -> Option<&mut Entry> {
+        self.entries.iter_mut().find(|e| e.id == id)
+    }
+
+    /// Moves entry `id` right before `target`, or right after it.
+    fn reorder(&mut self, id: EntryId, target: EntryId, after: bool) {
+        let position = |entries: &[Entry], id| entries.iter().position(|e| e.id == id);
+        if id == target || position(&self.entries, target).is_none() {
+            return;
+        }
+        if let Some(from) = position(&self.entries, id) {
+            let entry = self.entries.remove(from);
+            let to = position(&self.entries, target).unwrap_or(from);
+            self.entries.insert(if after { to + 1 } else { to }, entry);
+        }
+    }
+
+    fn toggle(&mut self, id: EntryId) {
+        if let Some(entry) = self.entry_mut(id) {
+            entry.set_completed(!entry.completed);
+        }
+    }
+
+    /// Starts editing an entry, or stops if it is already being edited.
+    pub fn toggle_edit(&mut self, id: EntryId) {
+        if self.editing == Some(id) {
+            self.editing = None;
+            self.edit_error = None;
+        } else if self.entry(id).is_some() {
+            self.editing = Some(id);
+            self.edit_error = None;
+        }
+    }
+
+    pub fn session(&self) -> Session {
+        Session {
+            list: self.list,
+            filter: self.filter.clone(),
+            sorts: self.sorts.clone(),
+            priority_filter: self.priority_filter,
+            tag_filter: self.tag_filter.clone(),
+            collapsed: self.collapsed.clone(),
+            value: self.value.clone(),
+            editing: self.editing,
+            edit_value: self.edit_value.clone(),
+        }
+    }
+
+    /// Applies a restored session, dropping what refers to entries that are
+    /// gone.
+    pub fn restore_session(&mut self, session: Session) {
+        self.list = session.list;
+        self.sorts = session.sorts;
+        self.fix_lists();
+        self.filter = session.filter;
+        self.priority_filter = session.priority_filter;
+        self.tag_filter = session.tag_filter;
+        self.collapsed = session
+            .collapsed
+            .into_iter()
+            .filter(|&id| self.entry(id).is_some())
+            .collect();
+        self.value = session.value;
+        self.editing = session.editing.filter(|&id| self.entry(id).is_some());
+        self.edit_value = if self.editing.is_some() {
+            session.edit_value
+        } else {
+            "".into()
+        };
+    }
+
+    fn remove(&mut self, id: EntryId) {
+        self.entries.retain(|e| e.id != id);
+    }
+}
@@ -0,0 +1,233 @@
+//! Inline syntax for the new todo input.
+//!
+//! Words like `#errands`, `!high`, `@phone`, `due:2026-11-01` or
+//! `tomorrow 5pm` are taken out of the description and set the matching
+//! fields of the new entry instead. Anything that isn't recognized stays
+//! in the description as typed, so the live preview is the only way to
+//! tell what a line will create.
+//!
+//! Days and times also read as ordinary words ("Watch Friday Night Lights",
+//! "Read John 3:16"), so they only count after a preposition, in `due:`, or
+//! among the fields at the end of the line. A 24-hour time like `17:30`
+//! needs a preposition or `due:` even there.
+
+use crate::due::{Date, Due, Now, Time};
+use crate::events::Action;
+use crate::model::{EntryId, ListId, Priority};
+use crate::validation;
+
+/// Words that only introduce a date or time, like "at" in "at 5pm". They
+/// are left in the description unless one follows.
+const PREPOSITIONS: [&str; 5] = ["at", "by", "on", "due", "next"];
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// The entry a line typed into the new todo input creates.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuickAdd {
+    /// The rest of the line, with single spaces between words.
+    pub description: String,
+    pub due: Option<Due>,
+    pub priority: Option<Priority>,
+    pub tags: Vec<String>,
+    pub context: Option<String>,
+}
+
+impl QuickAdd {
+    /// Parses `raw`, resolving relative dates like "tomorrow" against `now`.
+    pub fn parse(raw: &str, now: &Now) -> QuickAdd {
+        let mut parsed = QuickAdd::default();
+        let mut date = None;
+        let mut time = None;
+        let mut rest = Vec::new();
+        let words: Vec<&str> = raw.split_whitespace().collect();
+        let tail = tail(&words, now);
+        let mut idx = 0;
+        while idx < words.len() {
+            let word = words[idx];
+            let lower = word.to_lowercase();
+            idx += 1;
+            if let Some(tag) = word.strip_prefix('#').and_then(validation::tag) {
+                if !parsed.tags.contains(&tag) {
+                    parsed.tags.push(tag);
+                }
+            } else if let Some(priority) = priority(&lower) {
+                parsed.priority = Some(priority);
+            } else if let Some(context) = word.strip_prefix('@').and_then(validation::tag) {
+                // Contexts are named like tags.
+                parsed.context = Some(context);
+            } else if let Some(due) = lower.strip_prefix("due:").and_then(|d| due(d, now)) {
+                date = Some(due.date);
+                time = due.time.or(time);
+            } else if let Some(day) = day(&lower, now).filter(|_| idx > tail) {
+                date = Some(day);
+            } else if let Some(at) = twelve_hour(&lower).filter(|_| idx > tail) {
+                time = Some(at);
+            } else if PREPOSITIONS.contains(&lower.as_str()) && idx < words.len() {
+                let next = words[idx].to_lowercase();
+                if let Some(day) = day(&next, now) {
+                    date = Some(day);
+                    idx += 1;
+                } else if let Some(at) = time_of_day(&next) {
+                    time = Some(at);
+                    idx += 1;
+                } else {
+                    rest.push(word);
+                }
+            } else {
+                rest.push(word);
+            }
+        }
+        parsed.description = rest.join(" ");
+        parsed.due = match (date, time) {
+            (Some(date), time) => Some(Due { date, time }),
+            // A time on its own is the next time it comes around.
+            (None, Some(time)) if time < now.time => Some(Due {
+                date: now.date.add_days(1),
+                time: Some(time),
+            }),
+            (None, Some(time)) => Some(Due {
+                date: now.date,
+                time: Some(time),
+            }),
+            (None, None) => None,
+        };
+        parsed
+    }
+
+    /// Whether anything besides the description was recognized.
+    pub fn has_fields(&self) -> bool {
+        self.due.is_some()
+            || self.priority.is_some()
+            || !self.tags.is_empty()
+            || self.context.is_some()
+    }
+
+    /// The actions that create this as entry `id` of `list`.
+    pub fn actions(&self, id: EntryId, list: ListId) -> Vec<Action> {
+        let mut actions = vec![Action::Add {
+            id,
+            description: self.description.clone(),
+            list,
+        }];
+        if let Some(due) = self.due {
+            actions.push(Action::SetDue { id, due: Some(due) });
+        }
+        if let Some(priority) = self.priority {
+            actions.push(Action::SetPriority { id, priority });
+        }
+        for tag in &self.tags {
+            actions.push(Action::AddTag {
+                id,
+                tag: tag.clone(),
+            });
+        }
+        if let Some(context) = &self.context {
+            actions.push(Action::SetContext {
+                id,
+                context: Some(context.clone()),
+            });
+        }
+        actions
+    }
+}
+
+/// Where the run of fields at the end of `words` starts, or `words.len()`
+/// if the line ends in a plain word.
+fn tail(words: &[&str], now: &Now) -> usize {
+    let mut start = words.len();
+    while start > 0 {
+        let word = words[start - 1];
+        let lower = word.to_lowercase();
+        let field = word.strip_prefix('#').and_then(validation::tag).is_some()
+            || priority(&lower).is_some()
+            || word.strip_prefix('@').and_then(validation::tag).is_some()
+            || lower
+                .strip_prefix("due:")
+                .and_then(|d| due(d, now))
+                .is_some()
+            || day(&lower, now).is_some()
+            || twelve_hour(&lower).is_some();
+        if field {
+            start -= 1;
+        } else if start >= 2
+            && PREPOSITIONS.contains(&words[start - 2].to_lowercase().as_str())
+            && time_of_day(&lower).is_some()
+        {
+            start -= 2;
+        } else {
+            break;
+        }
+    }
+    start
+}
+
+/// `!low`, `!medium` or `!high`.
+fn priority(word: &str) -> Option<Priority> {
+    word.strip_prefix('!')
+        .and_then(|name| name.parse().ok())
+        .filter(|&priority| priority != Priority::None)
+}
+
+/// The value of a `due:` word: a date, a date and time as stored, or a
+/// day like `due:friday`.
+fn due(value: &str, now: &Now) -> Option<Due> {
+    value
+        .to_uppercase()
+        .parse()
+        .ok()
+        .or_else(|| day(value, now).map(|date| Due { date, time: None }))
+}
+
+/// `today`, `tomorrow` or the next day with a weekday's name.
+fn day(word: &str, now: &Now) -> Option<Date> {
+    match word {
+        "today" => Some(now.date),
+        "tomorrow" => Some(now.date.add_days(1)),
+        _ => {
+            let weekday = WEEKDAYS.iter().position(|&name| name == word)? as u32;
+            let ahead = (weekday + 7 - now.date.weekday()) % 7;
+            Some(now.date.add_days(if ahead == 0 { 7 } else { ahead }))
+        }
+    }
+}
+
+/// `5pm` or `5:30pm`, but not `17:30`.
+fn twelve_hour(word: &str) -> Option<Time> {
+    time_of_day(word).filter(|_| word.ends_with("am") || word.ends_with("pm"))
+}
+
+/// `17:30`, `5pm` or `5:30pm`.
+fn time_of_day(word: &str) -> Option<Time> {
+    let (clock, offset) = match word.strip_suffix("am") {
+        Some(clock) => (clock, Some(0)),
+        None => match word.strip_suffix("pm") {
+            Some(clock) => (clock, Some(12)),
+            None => (word, None),
+        },
+    };
+    if !clock.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    match offset {
+        None if clock.contains(':') => clock.parse().ok(),
+        None => None,
+        Some(offset) => {
+            let (hour, minute) = clock.split_once(':').unwrap_or((clock, "0"));
+            let hour: u32 = hour.parse().ok().filter(|h| (1..=12).contains(h))?;
+            let minute: u32 = minute.parse().ok().filter(|m| *m < 60)?;
+            Some(Time {
+                hour: hour % 12 + offset,
+                minute,
+            })
+        }
+    }
+}
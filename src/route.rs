@@ -0,0 +1,236 @@
+//! Hash-based routes like `#/active`, `#/list/work/overdue/tag/urgent`,
+//! `#/sort/due-desc` or `#/completed/entry/42`.
+//!
+//! The route is the source of truth for the selected filter: filter links
+//! only change the URL hash, and [`HashListener`] turns every `hashchange`,
+//! including the ones caused by the browser's back and forward buttons,
+//! into a message for the app.
+
+use crate::listener::WindowListener;
+use crate::model::{EntryId, Filter, Sort, SortOrder, State};
+use std::fmt;
+use strum::IntoEnumIterator;
+use wasm_bindgen::JsValue;
+use yew::Callback;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Route {
+    /// The name of the list shown. Without one, the first list is.
+    pub list: Option<String>,
+    pub filter: Filter,
+    /// How the list is sorted. Without one, it is sorted the way it was
+    /// last time.
+    pub sort: Option<SortOrder>,
+    /// Only entries with this tag are shown, if set.
+    pub tag: Option<String>,
+    /// An entry linked to directly, which is highlighted and scrolled to.
+    pub entry: Option<EntryId>,
+}
+
+impl Route {
+    /// Parses a location hash. Unknown segments are ignored, so a mistyped
+    /// link still shows the list.
+    pub fn parse(hash: &str) -> Route {
+        let mut route = Route::default();
+        let path = hash.trim_start_matches('#').trim_start_matches('/');
+        let mut segments = path.split('/').filter(|s| !s.is_empty()).peekable();
+        if segments.peek() == Some(&"list") {
+            segments.next();
+            route.list = segments.next().map(decode).filter(|name| !name.is_empty());
+        }
+        let filter = segments
+            .peek()
+            .and_then(|&segment| Filter::iter().find(|f| Some(segment) == filter_segment(f)));
+        if let Some(filter) = filter {
+            route.filter = filter;
+            segments.next();
+        }
+        if segments.peek() == Some(&"sort") {
+            segments.next();
+            route.sort = segments.next().and_then(sort_order);
+        }
+        if segments.peek() == Some(&"tag") {
+            segments.next();
+            route.tag = segments.next().map(decode).filter(|tag| !tag.is_empty());
+        }
+        if segments.next() == Some("entry") {
+            route.entry = segments.next().and_then(|id| id.parse().ok());
+        }
+        route
+    }
+
+    /// The route to what `state` shows: its list, filter, sort and tag.
+    pub fn of(state: &State) -> Route {
+        Route {
+            list: Some(state.current_list().name.clone()),
+            filter: state.filter.clone(),
+            sort: Some(state.sort()).filter(|&order| order != SortOrder::default()),
+            tag: state.tag_filter.clone(),
+            entry: None,
+        }
+    }
+
+    /// Makes `state` show this route's list, filter, sort and tag. A list
+    /// that doesn't exist, like one renamed since, shows the first list
+    /// instead.
+    pub fn show_in(&self, state: &mut State) {
+        let list = self.list.as_deref().and_then(|name| state.list_named(name));
+        state.list = list.unwrap_or(&state.lists()[0]).id;
+        if let Some(order) = self.sort {
+            state.set_sort(order);
+        }
+        state.filter = self.filter.clone();
+        state.tag_filter = self.tag.clone();
+    }
+
+    /// Adds the sort to the address bar if this route, shown in `state`,
+    /// left it out and the list has one.
+    pub fn add_missing_sort(&self, state: &State) {
+        let sort = Route::of(state).sort;
+        if self.sort.is_none() && sort.is_some() {
+            Route {
+                sort,
+                ..self.clone()
+            }
+            .replace();
+        }
+    }
+
+    /// The current location hash of the page.
+    pub fn current() -> Route {
+        web_sys::window()
+            .and_then(|window| window.location().hash().ok())
+            .map(|hash| Route::parse(&hash))
+            .unwrap_or_default()
+    }
+
+    /// Whether the page was opened without any route in its URL.
+    pub fn is_unset() -> bool {
+        web_sys::window()
+            .and_then(|window| window.location().hash().ok())
+            .is_none_or(|hash| hash.is_empty() || hash == "#")
+    }
+
+    /// Shows this route in the address bar without adding a history entry.
+    pub fn replace(&self) {
+        if let Some(history) = web_sys::window().and_then(|window| window.history().ok()) {
+            let _ = history.replace_state_with_url(&JsValue::NULL, "", Some(&self.to_string()));
+        }
+    }
+}
+
+impl From<Filter> for Route {
+    fn from(filter: Filter) -> Self {
+        Route {
+            list: None,
+            filter,
+            sort: None,
+            tag: None,
+            entry: None,
+        }
+    }
+}
+
+impl fmt::Display for Route {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut segments = Vec::new();
+        if let Some(list) = &self.list {
+            segments.push(format!("list/{}", encode(list)));
+        }
+        if let Some(segment) = filter_segment(&self.filter) {
+            segments.push(segment.to_string());
+        }
+        if let Some(order) = self.sort {
+            let direction = if order.descending { "-desc" } else { "" };
+            segments.push(format!("sort/{}{}", order.by, direction));
+        }
+        if let Some(tag) = &self.tag {
+            segments.push(format!("tag/{}", encode(tag)));
+        }
+        if let Some(id) = self.entry {
+            segments.push(format!("entry/{}", id));
+        }
+        write!(f, "#/{}", segments.join("/"))
+    }
+}
+
+/// The path segment of a filter. Showing all entries is the default and
+/// has none.
+fn filter_segment(filter: &Filter) -> Option<&'static str> {
+    match filter {
+        Filter::All => None,
+        Filter::Active => Some("active"),
+        Filter::Completed => Some("completed"),
+        Filter::Today => Some("today"),
+        Filter::Upcoming => Some("upcoming"),
+        Filter::Overdue => Some("overdue"),
+    }
+}
+
+/// Parses a sort segment like `due` or `due-desc`.
+fn sort_order(segment: &str) -> Option<SortOrder> {
+    let (by, descending) = match segment.strip_suffix("-desc") {
+        Some(by) => (by, true),
+        None => (segment, false),
+    };
+    Some(SortOrder {
+        by: by.parse::<Sort>().ok()?,
+        descending,
+    })
+}
+
+/// Percent-encodes everything but unreserved characters, so a tag or list
+/// name is a single path segment.
+fn encode(segment: &str) -> String {
+    let mut encoded = String::new();
+    for byte in segment.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+fn decode(segment: &str) -> String {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        let escaped = bytes
+            .get(idx + 1..idx + 3)
+            .filter(|_| bytes[idx] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                idx += 3;
+            }
+            None => {
+                decoded.push(bytes[idx]);
+                idx += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Calls back with the new route whenever the location hash changes, for
+/// as long as it is kept alive.
+pub struct HashListener {
+    _listener: WindowListener,
+}
+
+impl HashListener {
+    pub fn new(callback: Callback<Route>) -> Self {
+        let listener = WindowListener::new("hashchange", move |_| {
+            callback.emit(Route::current());
+        });
+        HashListener {
+            _listener: listener,
+        }
+    }
+}
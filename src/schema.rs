@@ -0,0 +1,419 @@
+//! Versioned format of the data persisted to storage.
+//!
+//! Everything is written as an [`Envelope`] tagged with [`CURRENT_VERSION`].
+//! When reading, the payload is upgraded one version at a time by the
+//! functions in [`MIGRATIONS`] before being deserialized into [`Persisted`],
+//! so adding a field to `Entry` only needs a new migration instead of
+//! wiping users' lists.
+
+use crate::model::{Entry, EntryId, List, Priority, DEFAULT_LIST};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::ops::Range;
+
+/// Version written by [`encode`].
+pub const CURRENT_VERSION: u32 = 10;
+
+/// `MIGRATIONS[n]` upgrades a version `n` payload to version `n + 1`.
+const MIGRATIONS: [fn(Value) -> Result<Value, SchemaError>; CURRENT_VERSION as usize] = [
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+    migrate_v8_to_v9,
+    migrate_v9_to_v10,
+];
+
+#[derive(Serialize)]
+struct Envelope<T> {
+    version: u32,
+    data: T,
+}
+
+/// The entry data as it is stored, in its current shape.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Persisted {
+    pub next_id: EntryId,
+    pub lists: Vec<List>,
+    pub entries: Vec<Entry>,
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    /// The payload is not valid JSON or does not match the expected shape.
+    Json(serde_json::Error),
+    /// The payload was written by a newer version of the app.
+    UnknownVersion(u32),
+    /// The payload is valid JSON but not in any known format.
+    Malformed(&'static str),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Json(err) => write!(f, "invalid data: {}", err),
+            SchemaError::UnknownVersion(version) => {
+                write!(f, "unsupported data version {}", version)
+            }
+            SchemaError::Malformed(reason) => write!(f, "malformed data: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl From<serde_json::Error> for SchemaError {
+    fn from(err: serde_json::Error) -> Self {
+        SchemaError::Json(err)
+    }
+}
+
+#[derive(Serialize)]
+struct PersistedRef<'a> {
+    next_id: EntryId,
+    lists: &'a [List],
+    entries: &'a [Entry],
+}
+
+/// Serializes the entry data into a current-version envelope.
+pub fn encode(next_id: EntryId, lists: &[List], entries: &[Entry]) -> String {
+    let envelope = Envelope {
+        version: CURRENT_VERSION,
+        data: PersistedRef {
+            next_id,
+            lists,
+            entries,
+        },
+    };
+    serde_json::to_string(&envelope).expect("persisted data is always serializable")
+}
+
+/// Like [`encode`], for embedding the envelope in a larger document.
+pub fn encode_value(next_id: EntryId, lists: &[List], entries: &[Entry]) -> Value {
+    let envelope = Envelope {
+        version: CURRENT_VERSION,
+        data: PersistedRef {
+            next_id,
+            lists,
+            entries,
+        },
+    };
+    serde_json::to_value(&envelope).expect("persisted data is always serializable")
+}
+
+/// Parses a payload of any known version, migrating it to the current shape.
+pub fn decode(raw: &str) -> Result<Persisted, SchemaError> {
+    decode_value(serde_json::from_str(raw)?)
+}
+
+/// Like [`decode`], for a payload that was already parsed.
+pub fn decode_value(value: Value) -> Result<Persisted, SchemaError> {
+    let (version, mut data) = split_envelope(value)?;
+    if version > CURRENT_VERSION {
+        return Err(SchemaError::UnknownVersion(version));
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        data = migration(data)?;
+    }
+    Ok(serde_json::from_value(data)?)
+}
+
+/// Migrates entries and lists that were stored in `version` as part of
+/// something else, like a logged action, to the current shape. Without
+/// lists, the entries get the default one, as before there were several.
+pub fn migrate_parts(
+    version: u32,
+    entries: Value,
+    lists: Option<Value>,
+) -> Result<Persisted, SchemaError> {
+    let lists = lists.unwrap_or_else(|| json!([List::default()]));
+    let data = json!({ "next_id": 0, "entries": entries, "lists": lists });
+    decode_value(json!({ "version": version, "data": data }))
+}
+
+/// Returns the version and data of a payload. Payloads from before the
+/// envelope was introduced are a bare array of entries and count as
+/// version 0.
+fn split_envelope(value: Value) -> Result<(u32, Value), SchemaError> {
+    match value {
+        Value::Array(_) => Ok((0, value)),
+        Value::Object(mut map) => {
+            let version = map
+                .get("version")
+                .and_then(Value::as_u64)
+                .ok_or(SchemaError::Malformed("missing version"))?;
+            let data = map
+                .remove("data")
+                .ok_or(SchemaError::Malformed("missing data"))?;
+            Ok((version as u32, data))
+        }
+        _ => Err(SchemaError::Malformed("expected an array or an envelope")),
+    }
+}
+
+/// Version 0 is a bare array of entries, which may lack an `id` if it was
+/// written before entries had one. Version 1 wraps the entries together
+/// with the next id to hand out.
+fn migrate_v0_to_v1(data: Value) -> Result<Value, SchemaError> {
+    let mut entries = match data {
+        Value::Array(entries) => entries,
+        _ => return Err(SchemaError::Malformed("expected an array of entries")),
+    };
+    let max_id = entries
+        .iter()
+        .filter_map(|e| e.get("id").and_then(Value::as_u64))
+        .max()
+        .unwrap_or(0);
+    let mut next_id = max_id + 1;
+    for entry in entries.iter_mut() {
+        let entry = entry
+            .as_object_mut()
+            .ok_or(SchemaError::Malformed("expected an entry object"))?;
+        if entry.get("id").and_then(Value::as_u64).unwrap_or(0) == 0 {
+            entry.insert("id".into(), json!(next_id));
+            next_id += 1;
+        }
+    }
+    Ok(json!({ "next_id": next_id, "entries": entries }))
+}
+
+/// Version 2 adds an optional due date to entries.
+fn migrate_v1_to_v2(mut data: Value) -> Result<Value, SchemaError> {
+    for entry in entries_mut(&mut data)? {
+        entry.entry("due").or_insert(Value::Null);
+    }
+    Ok(data)
+}
+
+/// Version 3 adds a priority to entries.
+fn migrate_v2_to_v3(mut data: Value) -> Result<Value, SchemaError> {
+    for entry in entries_mut(&mut data)? {
+        entry.entry("priority").or_insert(json!(Priority::None));
+    }
+    Ok(data)
+}
+
+/// Version 4 adds tags to entries.
+fn migrate_v3_to_v4(mut data: Value) -> Result<Value, SchemaError> {
+    for entry in entries_mut(&mut data)? {
+        entry.entry("tags").or_insert(json!([]));
+    }
+    Ok(data)
+}
+
+/// Version 5 adds an optional context to entries.
+fn migrate_v4_to_v5(mut data: Value) -> Result<Value, SchemaError> {
+    for entry in entries_mut(&mut data)? {
+        entry.entry("context").or_insert(Value::Null);
+    }
+    Ok(data)
+}
+
+/// Version 6 adds subtasks to entries, and whether they complete the entry.
+fn migrate_v5_to_v6(mut data: Value) -> Result<Value, SchemaError> {
+    for entry in entries_mut(&mut data)? {
+        entry.entry("subtasks").or_insert(json!([]));
+        entry.entry("auto_complete").or_insert(json!(false));
+    }
+    Ok(data)
+}
+
+/// Version 7 adds named lists, with every entry in one of them. Existing
+/// entries go to the default list.
+fn migrate_v6_to_v7(mut data: Value) -> Result<Value, SchemaError> {
+    for entry in entries_mut(&mut data)? {
+        entry.entry("list").or_insert(json!(DEFAULT_LIST));
+    }
+    if let Some(data) = data.as_object_mut() {
+        data.entry("lists")
+            .or_insert_with(|| json!([List::default()]));
+    }
+    Ok(data)
+}
+
+/// Version 8 records when entries last changed, which is unknown for the
+/// existing ones.
+fn migrate_v7_to_v8(mut data: Value) -> Result<Value, SchemaError> {
+    for entry in entries_mut(&mut data)? {
+        entry.entry("updated_at").or_insert(Value::Null);
+    }
+    Ok(data)
+}
+
+/// Version 9 also records when entries were added and completed. Neither
+/// is known for the existing ones.
+fn migrate_v8_to_v9(mut data: Value) -> Result<Value, SchemaError> {
+    for entry in entries_mut(&mut data)? {
+        entry.entry("created_at").or_insert(Value::Null);
+        entry.entry("completed_at").or_insert(Value::Null);
+    }
+    Ok(data)
+}
+
+/// Version 10 records when descriptions were last edited apart from other
+/// changes. That is unknown for the existing entries.
+fn migrate_v9_to_v10(mut data: Value) -> Result<Value, SchemaError> {
+    for entry in entries_mut(&mut data)? {
+        entry.entry("edited_at").or_insert(Value::Null);
+    }
+    Ok(data)
+}
+
+/// The entry objects of a version 1 or later payload.
+fn entries_mut(
+    data: &mut Value,
+) -> Result<impl Iterator<Item = &mut Map<String, Value>>, SchemaError> {
+    let entries = data
+        .get_mut("entries")
+        .and_then(Value::as_array_mut)
+        .ok_or(SchemaError::Malformed("expected a list of entries"))?;
+    if !entries.iter().all(Value::is_object) {
+        return Err(SchemaError::Malformed("expected an entry object"));
+    }
+    Ok(entries.iter_mut().filter_map(Value::as_object_mut))
+}
+
+/// Salvages whatever entries can still be read from a payload that
+/// [`decode`] rejected.
+///
+/// Every balanced `{ .. }` object in `raw` that has a string `description`
+/// and isn't part of another such object, like a subtask is, is taken as an
+/// entry, so this also works on truncated or otherwise invalid JSON. Missing
+/// or clashing ids are replaced with fresh ones. Objects with an `id` and a
+/// `name` instead are taken as lists.
+pub fn recover(raw: &str) -> Persisted {
+    let values: Vec<(Range<usize>, Value)> = objects(raw)
+        .filter_map(|(span, object)| Some((span, serde_json::from_str(object).ok()?)))
+        .collect();
+    let salvaged: Vec<(Range<usize>, Entry)> = values
+        .iter()
+        .filter_map(|(span, value)| Some((span.clone(), salvage_entry(value)?)))
+        .collect();
+    let nested = |span: &Range<usize>| {
+        salvaged
+            .iter()
+            .any(|(outer, _)| outer != span && outer.start <= span.start && span.end <= outer.end)
+    };
+    let mut entries: Vec<Entry> = salvaged
+        .iter()
+        .filter(|(span, _)| !nested(span))
+        .map(|(_, entry)| entry.clone())
+        .collect();
+
+    let mut lists: Vec<List> = Vec::new();
+    for list in values.iter().filter_map(|(_, value)| salvage_list(value)) {
+        if !lists.iter().any(|l| l.id == list.id) {
+            lists.push(list);
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    let mut next_id = entries
+        .iter()
+        .flat_map(|e| std::iter::once(e.id).chain(e.subtasks.iter().map(|s| s.id)))
+        .chain(lists.iter().map(|l| l.id))
+        .max()
+        .unwrap_or(0)
+        + 1;
+    for entry in entries.iter_mut() {
+        if entry.id == 0 || !seen.insert(entry.id) {
+            entry.id = next_id;
+            next_id += 1;
+        }
+    }
+    Persisted {
+        next_id,
+        lists,
+        entries,
+    }
+}
+
+fn salvage_list(value: &Value) -> Option<List> {
+    if value.get("description").is_some() {
+        return None;
+    }
+    Some(List {
+        id: value.get("id")?.as_u64()?,
+        name: value.get("name")?.as_str()?.to_string(),
+    })
+}
+
+fn salvage_entry(value: &Value) -> Option<Entry> {
+    Some(Entry {
+        id: value.get("id").and_then(Value::as_u64).unwrap_or(0),
+        list: value
+            .get("list")
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_LIST),
+        description: value.get("description")?.as_str()?.to_string(),
+        completed: value
+            .get("completed")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        due: value
+            .get("due")
+            .and_then(Value::as_str)
+            .and_then(|due| due.parse().ok()),
+        priority: value
+            .get("priority")
+            .and_then(Value::as_str)
+            .and_then(|priority| priority.parse().ok())
+            .unwrap_or_default(),
+        tags: value
+            .get("tags")
+            .and_then(|tags| serde_json::from_value(tags.clone()).ok())
+            .unwrap_or_default(),
+        context: value
+            .get("context")
+            .and_then(Value::as_str)
+            .map(str::to_string),
+        subtasks: value
+            .get("subtasks")
+            .and_then(|subtasks| serde_json::from_value(subtasks.clone()).ok())
+            .unwrap_or_default(),
+        auto_complete: value
+            .get("auto_complete")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        created_at: value.get("created_at").and_then(Value::as_f64),
+        updated_at: value.get("updated_at").and_then(Value::as_f64),
+        edited_at: value.get("edited_at").and_then(Value::as_f64),
+        completed_at: value.get("completed_at").and_then(Value::as_f64),
+    })
+}
+
+/// Iterates over the span and text of every balanced JSON object in `raw`,
+/// innermost objects first, ignoring braces inside strings.
+fn objects(raw: &str) -> impl Iterator<Item = (Range<usize>, &str)> {
+    let mut found = Vec::new();
+    let mut starts = Vec::new();
+    let mut in_string = false;
+    let mut escaped = false;
+    for (idx, c) in raw.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => starts.push(idx),
+            '}' => {
+                if let Some(start) = starts.pop() {
+                    found.push((start..idx + 1, &raw[start..=idx]));
+                }
+            }
+            _ => {}
+        }
+    }
+    found.into_iter()
+}
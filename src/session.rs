@@ -0,0 +1,38 @@
+//! UI state that survives reloads: the selected list and filter, how each
+//! list is sorted, the half-typed new todo and the entry being edited.
+//!
+//! It is stored under its own key, apart from the entries, so resetting it
+//! never touches the todos. The session is disposable, so anything that
+//! can't be read is simply replaced with the defaults.
+
+use crate::model::{EntryId, Filter, ListId, Priority, SortOrder};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub const SESSION_KEY: &str = "yew.todomvc.session";
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub list: ListId,
+    pub filter: Filter,
+    /// How each list is sorted, if not in list order.
+    pub sorts: BTreeMap<ListId, SortOrder>,
+    pub priority_filter: Option<Priority>,
+    pub tag_filter: Option<String>,
+    /// Entries whose subtasks are hidden.
+    pub collapsed: BTreeSet<EntryId>,
+    pub value: String,
+    pub editing: Option<EntryId>,
+    pub edit_value: String,
+}
+
+impl Session {
+    pub fn decode(raw: &str) -> Session {
+        serde_json::from_str(raw).unwrap_or_default()
+    }
+
+    pub fn encode(&self) -> String {
+        serde_json::to_string(self).expect("session is always serializable")
+    }
+}
@@ -0,0 +1,194 @@
+//! Persistence backends the app can keep its data in.
+//!
+//! The preferred [`Backend`] is chosen by cargo feature (`indexeddb-storage`,
+//! `session-storage` or `memory-storage`, localStorage otherwise) and can be
+//! overridden at runtime with a `?storage=local|session|memory|indexeddb`
+//! query parameter. If the preferred backend can't be used, e.g. because the
+//! browser blocks web storage in private mode or in a sandboxed iframe,
+//! [`open`] falls back to the next one and finally to memory, so the app
+//! keeps working without persistence instead of crashing.
+//!
+//! IndexedDB is asynchronous and stores entries and events record by
+//! record, so it isn't a [`Storage`]; see [`IdbStore`]. When it is preferred, [`open`]
+//! still provides a key-value backend for everything else.
+
+mod idb;
+
+pub use idb::{IdbStore, Stored};
+
+use std::collections::HashMap;
+use std::fmt;
+use strum_macros::{Display, EnumString};
+
+/// Key written and removed again to check that a web storage is usable.
+const PROBE_KEY: &str = "yew.todomvc.probe";
+
+/// A key-value store for serialized app data.
+pub trait Storage {
+    /// Which backend this is.
+    fn backend(&self) -> Backend;
+
+    fn load(&self, key: &str) -> Option<String>;
+
+    fn store(&mut self, key: &str, value: &str) -> Result<(), StorageError>;
+
+    fn remove(&mut self, key: &str);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Display, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum Backend {
+    Local,
+    Session,
+    Memory,
+    #[strum(serialize = "indexeddb")]
+    IndexedDb,
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        if cfg!(feature = "memory-storage") {
+            Backend::Memory
+        } else if cfg!(feature = "indexeddb-storage") {
+            Backend::IndexedDb
+        } else if cfg!(feature = "session-storage") {
+            Backend::Session
+        } else {
+            Backend::Local
+        }
+    }
+}
+
+impl Backend {
+    /// Reads a `storage=` override from a URL query string like
+    /// `?storage=session`.
+    pub fn from_query(query: &str) -> Option<Backend> {
+        query
+            .trim_start_matches('?')
+            .split('&')
+            .filter_map(|pair| pair.strip_prefix("storage="))
+            .find_map(|name| name.parse().ok())
+    }
+
+    /// Backends to try, in order, when this one is preferred.
+    fn fallbacks(self) -> Vec<Backend> {
+        let mut order = vec![self];
+        for backend in [Backend::Local, Backend::Session, Backend::Memory] {
+            if !order.contains(&backend) {
+                order.push(backend);
+            }
+        }
+        order
+    }
+}
+
+#[derive(Debug)]
+pub enum StorageError {
+    /// The backend refused the write, usually because its quota is used up.
+    Rejected(String),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StorageError::Rejected(reason) => write!(f, "couldn't save: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+/// Opens the first usable key-value backend, starting with `preferred`.
+pub fn open(preferred: Backend) -> Box<dyn Storage> {
+    for backend in preferred.fallbacks() {
+        let storage: Option<Box<dyn Storage>> = match backend {
+            Backend::Local | Backend::Session => {
+                WebStorage::open(backend).map(|s| Box::new(s) as Box<dyn Storage>)
+            }
+            Backend::Memory => Some(Box::new(MemoryStorage::default())),
+            Backend::IndexedDb => continue,
+        };
+        match storage {
+            Some(storage) => return storage,
+            None => log::warn!("{} storage is unavailable", backend),
+        }
+    }
+    unreachable!("memory storage is always available")
+}
+
+/// The preferred backend for this build, taking the runtime override in the
+/// page URL into account.
+pub fn preferred() -> Backend {
+    web_sys::window()
+        .and_then(|window| window.location().search().ok())
+        .and_then(|query| Backend::from_query(&query))
+        .unwrap_or_default()
+}
+
+/// `localStorage` or `sessionStorage`.
+pub struct WebStorage {
+    backend: Backend,
+    storage: web_sys::Storage,
+}
+
+impl WebStorage {
+    /// Returns `None` if the storage area is missing, access to it throws,
+    /// or it refuses writes.
+    pub fn open(backend: Backend) -> Option<Self> {
+        let window = web_sys::window()?;
+        let storage = match backend {
+            Backend::Local => window.local_storage(),
+            Backend::Session => window.session_storage(),
+            Backend::Memory | Backend::IndexedDb => return None,
+        };
+        let storage = storage.ok().flatten()?;
+        storage.set_item(PROBE_KEY, PROBE_KEY).ok()?;
+        storage.remove_item(PROBE_KEY).ok()?;
+        Some(WebStorage { backend, storage })
+    }
+}
+
+impl Storage for WebStorage {
+    fn backend(&self) -> Backend {
+        self.backend
+    }
+
+    fn load(&self, key: &str) -> Option<String> {
+        self.storage.get_item(key).ok().flatten()
+    }
+
+    fn store(&mut self, key: &str, value: &str) -> Result<(), StorageError> {
+        self.storage
+            .set_item(key, value)
+            .map_err(|err| StorageError::Rejected(format!("{:?}", err)))
+    }
+
+    fn remove(&mut self, key: &str) {
+        let _ = self.storage.remove_item(key);
+    }
+}
+
+/// Keeps data for the lifetime of the page only.
+#[derive(Default)]
+pub struct MemoryStorage {
+    items: HashMap<String, String>,
+}
+
+impl Storage for MemoryStorage {
+    fn backend(&self) -> Backend {
+        Backend::Memory
+    }
+
+    fn load(&self, key: &str) -> Option<String> {
+        self.items.get(key).cloned()
+    }
+
+    fn store(&mut self, key: &str, value: &str) -> Result<(), StorageError> {
+        self.items.insert(key.to_string(), value.to_string());
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &str) {
+        self.items.remove(key);
+    }
+}
@@ -0,0 +1,556 @@
+//! IndexedDB persistence for entries and the event log.
+//!
+//! Unlike the key-value [`Storage`](super::Storage) backends, which rewrite
+//! the whole list as one string on every change, this keeps one record per
+//! entry and each [`IdbStore::sync`] only writes the entries that changed
+//! since the last one. Records are stored in the current [`schema`] shape,
+//! together with a meta record holding the schema version, the next id, the
+//! lists and the order of the entries, so they go through the same
+//! migrations as everything else when loaded. Keeping the order there means
+//! moving or removing an entry only rewrites the meta record, not every
+//! entry after it.
+//!
+//! The [`EventLog`] is kept the same way: one record per event, numbered
+//! from the start of the log, and a log record with the snapshot that is
+//! only rewritten when the log is compacted. The undo history, which only
+//! holds what each step changes, is one more record in the meta store.
+
+use crate::events::EventLog;
+use crate::model::{Entry, EntryId, List, State};
+use crate::schema::{self, Persisted};
+use js_sys::{Array, Promise};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{spawn_local, JsFuture};
+use web_sys::{
+    Event, IdbDatabase, IdbKeyRange, IdbOpenDbRequest, IdbRequest, IdbTransaction,
+    IdbTransactionMode,
+};
+use yew::Callback;
+
+const DB_NAME: &str = "yew.todomvc";
+const DB_VERSION: u32 = 2;
+const ENTRIES: &str = "entries";
+const META: &str = "meta";
+const EVENTS: &str = "events";
+const META_KEY: &str = "meta";
+/// Key of the event log's snapshot in the meta store.
+const LOG_KEY: &str = "log";
+/// Key of the encoded undo history in the meta store.
+const HISTORY_KEY: &str = "history";
+
+pub struct IdbStore {
+    db: IdbDatabase,
+    /// What was last handed to the database, by entry id.
+    written: HashMap<EntryId, Entry>,
+    /// The next id, lists and entry order last written to the meta record.
+    written_meta: Option<Meta>,
+    /// Whether the next write starts by clearing the entry records, because
+    /// a failed write left them unknown.
+    clear: bool,
+    /// The numbers of the first event written and of the one after the
+    /// last, if the log record and events in the database are known.
+    written_log: Option<(u64, u64)>,
+}
+
+/// What was found in the database when it was opened.
+#[derive(Debug)]
+pub struct Stored {
+    pub entries: Persisted,
+    /// The event log, unless there was none that leads to `entries`.
+    pub log: Option<EventLog>,
+    /// The encoded undo history, if one was stored.
+    pub history: Option<String>,
+}
+
+#[derive(PartialEq)]
+struct Meta {
+    next_id: EntryId,
+    lists: Vec<List>,
+    order: Vec<EntryId>,
+}
+
+/// Everything a sync writes, in a single transaction.
+#[derive(Default)]
+struct Changes {
+    /// Whether the entry records are cleared before anything is written.
+    clear: bool,
+    puts: Vec<(EntryId, String)>,
+    deletes: Vec<EntryId>,
+    meta: Option<String>,
+    /// Whether the events are cleared before any are written.
+    clear_events: bool,
+    log: Option<String>,
+    /// Events numbered below this were folded into the log's snapshot.
+    folded: Option<u64>,
+    events: Vec<(u64, String)>,
+    history: Option<String>,
+}
+
+/// The stored data as read, before it is migrated.
+struct Records {
+    /// The schema version the entries were written in.
+    version: u32,
+    /// Whether the meta record holds the entry order.
+    ordered: bool,
+    /// The entry data in that version's shape.
+    data: Value,
+    /// The log record and its events, if a log was written.
+    log: Option<(Value, Vec<Value>)>,
+    history: Option<String>,
+}
+
+impl fmt::Debug for IdbStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("IdbStore")
+    }
+}
+
+impl IdbStore {
+    pub fn is_supported() -> bool {
+        web_sys::window()
+            .and_then(|window| window.indexed_db().ok().flatten())
+            .is_some()
+    }
+
+    /// Opens the database and loads its entries and event log, migrated to
+    /// the current schema. Resolves to `None` if nothing was stored yet.
+    pub async fn open() -> Result<(IdbStore, Option<Stored>), String> {
+        let db = open_db().await?;
+        let mut store = IdbStore {
+            db,
+            written: HashMap::new(),
+            written_meta: None,
+            clear: false,
+            written_log: None,
+        };
+        let records = match store.load().await? {
+            Some(records) => records,
+            None => return Ok((store, None)),
+        };
+        let current = records.version == schema::CURRENT_VERSION;
+        let entries = schema::decode_value(records.data).map_err(|err| err.to_string())?;
+        // Records in an older shape are all rewritten on the next sync, and
+        // an order kept in the records themselves moves to the meta record.
+        if current {
+            store.written = entries
+                .entries
+                .iter()
+                .map(|entry| (entry.id, entry.clone()))
+                .collect();
+        }
+        if current && records.ordered {
+            store.written_meta = Some(Meta {
+                next_id: entries.next_id,
+                lists: entries.lists.clone(),
+                order: entries.entries.iter().map(|e| e.id).collect(),
+            });
+        }
+        // A log that doesn't lead to the entries, say because it couldn't
+        // be read, is started over by the app.
+        let log = records
+            .log
+            .and_then(|(record, events)| decode_log(record, events))
+            .filter(|(log, _)| log.replay().entries() == entries.entries.as_slice());
+        if let Some((log, version)) = &log {
+            if *version == schema::CURRENT_VERSION {
+                let end = log.folded + log.events.len() as u64;
+                store.written_log = Some((log.folded, end));
+            }
+        }
+        let log = log.map(|(log, _)| log);
+        let history = records.history;
+        Ok((
+            store,
+            Some(Stored {
+                entries,
+                log,
+                history,
+            }),
+        ))
+    }
+
+    /// Writes the changes to the entries, the events recorded since the
+    /// last sync and the undo `history`, if it changed, in a single
+    /// transaction. Failures, such as running out of quota, are passed to
+    /// `on_error`.
+    pub fn sync(
+        &mut self,
+        state: &State,
+        log: &EventLog,
+        history: Option<String>,
+        on_error: Callback<String>,
+    ) {
+        let mut changes = Changes {
+            history,
+            ..Changes::default()
+        };
+        self.sync_entries(state, &mut changes);
+        self.sync_log(log, &mut changes);
+        if changes.puts.is_empty()
+            && changes.deletes.is_empty()
+            && changes.meta.is_none()
+            && changes.log.is_none()
+            && changes.events.is_empty()
+            && changes.history.is_none()
+        {
+            return;
+        }
+        changes.clear = std::mem::take(&mut self.clear);
+        let db = self.db.clone();
+        spawn_local(async move {
+            if let Err(err) = write(&db, changes).await {
+                on_error.emit(err);
+            }
+        });
+    }
+
+    fn sync_entries(&mut self, state: &State, changes: &mut Changes) {
+        let entries = state.entries();
+        for entry in entries {
+            if self.written.get(&entry.id) != Some(entry) {
+                let record = json!({ "entry": entry });
+                changes.puts.push((entry.id, record.to_string()));
+                self.written.insert(entry.id, entry.clone());
+            }
+        }
+        let ids: HashSet<EntryId> = entries.iter().map(|e| e.id).collect();
+        changes.deletes = self
+            .written
+            .keys()
+            .filter(|id| !ids.contains(id))
+            .copied()
+            .collect();
+        for id in &changes.deletes {
+            self.written.remove(id);
+        }
+        let current = Meta {
+            next_id: state.next_id(),
+            lists: state.lists().to_vec(),
+            order: entries.iter().map(|e| e.id).collect(),
+        };
+        if self.written_meta.as_ref() != Some(&current) {
+            let meta = json!({
+                "version": schema::CURRENT_VERSION,
+                "next_id": current.next_id,
+                "lists": current.lists,
+                "order": current.order,
+            });
+            changes.meta = Some(meta.to_string());
+            self.written_meta = Some(current);
+        }
+    }
+
+    fn sync_log(&mut self, log: &EventLog, changes: &mut Changes) {
+        let end = log.folded + log.events.len() as u64;
+        let from = match self.written_log {
+            // Events are only ever added, or folded into the snapshot.
+            Some((folded, written_end)) if folded <= log.folded && written_end <= end => {
+                if folded < log.folded {
+                    changes.log = Some(log_record(log));
+                    changes.folded = Some(log.folded);
+                }
+                written_end.max(log.folded)
+            }
+            _ => {
+                changes.log = Some(log_record(log));
+                changes.clear_events = true;
+                log.folded
+            }
+        };
+        changes.events = log.events[(from - log.folded) as usize..]
+            .iter()
+            .zip(from..)
+            .map(|(event, number)| {
+                let event = serde_json::to_string(event).expect("events are serializable");
+                (number, event)
+            })
+            .collect();
+        self.written_log = Some((log.folded, end));
+    }
+
+    /// Forgets what was written, so the next sync clears the records and
+    /// rewrites every entry and the log. Used after a failed write, when
+    /// the database contents are unknown; clearing them drops entries whose
+    /// deletion was rolled back.
+    pub fn invalidate(&mut self) {
+        self.written.clear();
+        self.written_meta = None;
+        self.clear = true;
+        self.written_log = None;
+    }
+
+    /// Has the next sync rewrite the log, which was replaced rather than
+    /// added to.
+    pub fn forget_log(&mut self) {
+        self.written_log = None;
+    }
+
+    /// Reads the meta record, all entry records and the event log.
+    async fn load(&self) -> Result<Option<Records>, String> {
+        let tx = self
+            .db
+            .transaction_with_str_sequence(&store_names())
+            .map_err(describe)?;
+        // Issue all requests before waiting, so the transaction can't
+        // finish in between.
+        let meta = tx
+            .object_store(META)
+            .and_then(|store| store.get(&META_KEY.into()))
+            .map_err(describe)?;
+        let records = tx
+            .object_store(ENTRIES)
+            .and_then(|store| store.get_all())
+            .map_err(describe)?;
+        let log = tx
+            .object_store(META)
+            .and_then(|store| store.get(&LOG_KEY.into()))
+            .map_err(describe)?;
+        let events = tx
+            .object_store(EVENTS)
+            .and_then(|store| store.get_all())
+            .map_err(describe)?;
+        let history = tx
+            .object_store(META)
+            .and_then(|store| store.get(&HISTORY_KEY.into()))
+            .map_err(describe)?;
+        let meta = request_done(&meta).await?;
+        let records = request_done(&records).await?;
+        let log = request_done(&log).await?;
+        let events = request_done(&events).await?;
+        let history = request_done(&history).await?;
+
+        let meta: Value = match meta.as_string() {
+            Some(meta) => serde_json::from_str(&meta).map_err(|err| err.to_string())?,
+            None => return Ok(None),
+        };
+        let version = meta["version"].as_u64().unwrap_or(0) as u32;
+        let mut records = parse_all(records);
+        // Records from before the order was kept in the meta record have
+        // their position in them instead.
+        let order: Option<HashMap<u64, usize>> = meta["order"].as_array().map(|order| {
+            order
+                .iter()
+                .filter_map(Value::as_u64)
+                .enumerate()
+                .map(|(position, id)| (id, position))
+                .collect()
+        });
+        records.sort_by_key(|record| match &order {
+            Some(order) => record["entry"]["id"]
+                .as_u64()
+                .and_then(|id| order.get(&id).copied()),
+            None => record["position"].as_u64().map(|p| p as usize),
+        });
+        let entries: Vec<Value> = records
+            .into_iter()
+            .map(|mut record| record["entry"].take())
+            .collect();
+        let mut data = json!({ "next_id": meta["next_id"], "entries": entries });
+        // Meta records from before there were several lists have none.
+        if let Some(lists) = meta.get("lists") {
+            data["lists"] = lists.clone();
+        }
+        let log = log
+            .as_string()
+            .and_then(|log| serde_json::from_str(&log).ok())
+            .map(|log| (log, parse_all(events)));
+        Ok(Some(Records {
+            version,
+            ordered: order.is_some(),
+            data: json!({ "version": version, "data": data }),
+            log,
+            history: history.as_string(),
+        }))
+    }
+}
+
+/// Parses the JSON strings `get_all` resolved to, in key order, skipping
+/// any that can't be read.
+fn parse_all(values: JsValue) -> Vec<Value> {
+    values
+        .unchecked_into::<Array>()
+        .iter()
+        .filter_map(|value| value.as_string())
+        .filter_map(|value| serde_json::from_str(&value).ok())
+        .collect()
+}
+
+/// The log record for `log`: its snapshot, and the number of its first
+/// event.
+fn log_record(log: &EventLog) -> String {
+    let snapshot = &log.snapshot;
+    let record = json!({
+        "snapshot": schema::encode_value(snapshot.next_id, &snapshot.lists, &snapshot.entries),
+        "folded": log.folded,
+    });
+    record.to_string()
+}
+
+/// Rebuilds the log from its record and events, returning it with the
+/// schema version it was written in.
+fn decode_log(mut record: Value, events: Vec<Value>) -> Option<(EventLog, u32)> {
+    let version = record["snapshot"]["version"].as_u64()? as u32;
+    let raw = json!({ "snapshot": record["snapshot"].take(), "events": events });
+    let mut log = EventLog::decode_value(raw).ok()?;
+    log.folded = record["folded"].as_u64().unwrap_or(0);
+    Some((log, version))
+}
+
+fn store_names() -> JsValue {
+    Array::of3(&ENTRIES.into(), &META.into(), &EVENTS.into()).into()
+}
+
+async fn open_db() -> Result<IdbDatabase, String> {
+    let factory = web_sys::window()
+        .ok_or("no window")?
+        .indexed_db()
+        .map_err(describe)?
+        .ok_or("IndexedDB is unavailable")?;
+    let request = factory
+        .open_with_u32(DB_NAME, DB_VERSION)
+        .map_err(describe)?;
+    let on_upgrade = Closure::wrap(Box::new(|event: Event| {
+        if let Some(db) = opened_db(&event) {
+            for name in [ENTRIES, META, EVENTS] {
+                if !db.object_store_names().contains(name) {
+                    let _ = db.create_object_store(name);
+                }
+            }
+        }
+    }) as Box<dyn FnMut(Event)>);
+    request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+    let opened = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+        // Another tab with an older version open holds the upgrade back
+        // until it closes, which may be never, so give up and let the app
+        // fall back to another backend.
+        request.set_onblocked(Some(&reject));
+    });
+    let done = JsFuture::from(opened).await;
+    request.set_onblocked(None);
+    if let Ok(Some(err)) = request.error() {
+        return Err(format!("{}: {}", err.name(), err.message()));
+    }
+    let db = match (done, request.result()) {
+        (Ok(_), Ok(db)) => db.unchecked_into::<IdbDatabase>(),
+        _ => {
+            // The upgrade still runs once the other tab closes, and the
+            // connection it opens is closed right away.
+            on_upgrade.forget();
+            let on_success = Closure::once_into_js(|event: Event| {
+                if let Some(db) = opened_db(&event) {
+                    db.close();
+                }
+            });
+            request.set_onsuccess(Some(on_success.unchecked_ref()));
+            request.set_onerror(None);
+            return Err("the database is open in another tab with an older version".into());
+        }
+    };
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+    request.set_onupgradeneeded(None);
+    // Close when another tab upgrades or deletes the database, rather than
+    // holding it back; writes fail from then on and the app says so.
+    let closing = db.clone();
+    let on_version_change =
+        Closure::wrap(Box::new(move |_: Event| closing.close()) as Box<dyn FnMut(Event)>);
+    db.set_onversionchange(Some(on_version_change.as_ref().unchecked_ref()));
+    on_version_change.forget();
+    Ok(db)
+}
+
+/// The database an open request's `event` is for.
+fn opened_db(event: &Event) -> Option<IdbDatabase> {
+    event
+        .target()
+        .and_then(|target| target.dyn_into::<IdbOpenDbRequest>().ok())
+        .and_then(|request| request.result().ok())
+        .and_then(|db| db.dyn_into::<IdbDatabase>().ok())
+}
+
+async fn write(db: &IdbDatabase, changes: Changes) -> Result<(), String> {
+    let tx = db
+        .transaction_with_str_sequence_and_mode(&store_names(), IdbTransactionMode::Readwrite)
+        .map_err(describe)?;
+    let entries = tx.object_store(ENTRIES).map_err(describe)?;
+    if changes.clear {
+        entries.clear().map_err(describe)?;
+    }
+    for (id, record) in changes.puts {
+        entries
+            .put_with_key(&record.into(), &(id as f64).into())
+            .map_err(describe)?;
+    }
+    for id in changes.deletes {
+        entries.delete(&(id as f64).into()).map_err(describe)?;
+    }
+    let meta = tx.object_store(META).map_err(describe)?;
+    if let Some(record) = changes.meta {
+        meta.put_with_key(&record.into(), &META_KEY.into())
+            .map_err(describe)?;
+    }
+    if let Some(record) = changes.log {
+        meta.put_with_key(&record.into(), &LOG_KEY.into())
+            .map_err(describe)?;
+    }
+    if let Some(history) = changes.history {
+        meta.put_with_key(&history.into(), &HISTORY_KEY.into())
+            .map_err(describe)?;
+    }
+    let events = tx.object_store(EVENTS).map_err(describe)?;
+    if changes.clear_events {
+        events.clear().map_err(describe)?;
+    }
+    if let Some(folded) = changes.folded {
+        let below =
+            IdbKeyRange::upper_bound_with_open(&(folded as f64).into(), true).map_err(describe)?;
+        events.delete(&below).map_err(describe)?;
+    }
+    for (number, event) in changes.events {
+        events
+            .put_with_key(&event.into(), &(number as f64).into())
+            .map_err(describe)?;
+    }
+    transaction_done(&tx).await
+}
+
+async fn request_done(request: &IdbRequest) -> Result<JsValue, String> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    let done = JsFuture::from(promise).await;
+    request.set_onsuccess(None);
+    request.set_onerror(None);
+    match done {
+        Ok(_) => request.result().map_err(describe),
+        Err(err) => Err(match request.error() {
+            Ok(Some(err)) => format!("{}: {}", err.name(), err.message()),
+            _ => describe(err),
+        }),
+    }
+}
+
+async fn transaction_done(tx: &IdbTransaction) -> Result<(), String> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        tx.set_oncomplete(Some(&resolve));
+        tx.set_onerror(Some(&reject));
+        tx.set_onabort(Some(&reject));
+    });
+    let done = JsFuture::from(promise).await;
+    match (done, tx.error()) {
+        (Ok(_), _) => Ok(()),
+        (Err(_), Some(err)) => Err(format!("{}: {}", err.name(), err.message())),
+        (Err(err), None) => Err(describe(err)),
+    }
+}
+
+fn describe(err: JsValue) -> String {
+    err.as_string().unwrap_or_else(|| format!("{:?}", err))
+}
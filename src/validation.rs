@@ -0,0 +1,92 @@
+//! Checks applied to descriptions, tags and list names before they are
+//! added or saved.
+
+use crate::model::{Entry, EntryId, List, ListId};
+use serde_derive::{Deserialize, Serialize};
+use std::fmt;
+
+/// Longest description accepted, in characters.
+pub const MAX_LENGTH: usize = 200;
+/// Longest tag accepted, in characters.
+pub const MAX_TAG_LENGTH: usize = 40;
+/// Longest list name accepted, in characters.
+pub const MAX_LIST_NAME_LENGTH: usize = 40;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Invalid {
+    /// Nothing but whitespace was entered.
+    Empty,
+    /// The description has `length` characters, more than [`MAX_LENGTH`].
+    TooLong { length: usize },
+}
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Invalid::Empty => f.write_str("Type something to add a todo."),
+            Invalid::TooLong { length } => write!(
+                f,
+                "Keep it under {} characters ({} now).",
+                MAX_LENGTH + 1,
+                length
+            ),
+        }
+    }
+}
+
+/// Trims the description and checks that it is neither empty nor too long.
+pub fn description(raw: &str) -> Result<String, Invalid> {
+    let trimmed = raw.trim();
+    let length = trimmed.chars().count();
+    if length == 0 {
+        Err(Invalid::Empty)
+    } else if length > MAX_LENGTH {
+        Err(Invalid::TooLong { length })
+    } else {
+        Ok(trimmed.to_string())
+    }
+}
+
+/// Finds another entry with the same description, ignoring case and
+/// surrounding whitespace. Duplicates are allowed, but worth a warning.
+pub fn duplicate<'a>(
+    entries: &'a [Entry],
+    raw: &str,
+    except: Option<EntryId>,
+) -> Option<&'a Entry> {
+    let wanted = raw.trim().to_lowercase();
+    if wanted.is_empty() {
+        return None;
+    }
+    entries
+        .iter()
+        .filter(|e| Some(e.id) != except)
+        .find(|e| e.description.trim().to_lowercase() == wanted)
+}
+
+/// Normalizes a tag as typed: without a leading `#`, lowercase and with
+/// dashes for spaces. Returns `None` if nothing usable is left or it is
+/// too long.
+pub fn tag(raw: &str) -> Option<String> {
+    let words: Vec<String> = raw
+        .trim()
+        .trim_start_matches('#')
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect();
+    let tag = words.join("-");
+    let length = tag.chars().count();
+    Some(tag).filter(|_| (1..=MAX_TAG_LENGTH).contains(&length))
+}
+
+/// Trims a list name and collapses its whitespace. Returns `None` if it is
+/// empty, too long or, ignoring case, the name of a list other than
+/// `except`, as lists are linked to by name.
+pub fn list_name(lists: &[List], raw: &str, except: Option<ListId>) -> Option<String> {
+    let name = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    let length = name.chars().count();
+    let taken = lists
+        .iter()
+        .any(|l| Some(l.id) != except && l.name.to_lowercase() == name.to_lowercase());
+    Some(name).filter(|_| (1..=MAX_LIST_NAME_LENGTH).contains(&length) && !taken)
+}
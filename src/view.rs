@@ -0,0 +1,696 @@
+//! Markup for the todo list itself.
+//!
+//! These are plain functions of [`State`] that send their messages through a
+//! callback instead of a `ComponentLink`, so they can be rendered and
+//! compared outside a browser. `App::view` wraps them in its notices and
+//! panels.
+
+use crate::app::Msg;
+use crate::due::{Due, Urgency};
+use crate::model::{Entry, EntryId, Filter, List, Priority, Sort, SortOrder, State};
+use crate::quick_add::QuickAdd;
+use crate::route::Route;
+use crate::validation::{self, Invalid};
+use strum::IntoEnumIterator;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+/// The id of the list of existing tags that tag inputs suggest from.
+const TAG_SUGGESTIONS: &str = "tag-suggestions";
+
+/// The input, the list of visible entries and the footer below it.
+/// `linked` is the entry opened through a deep link, which is highlighted.
+/// `edit_input` is attached to the input of the entry being edited.
+pub fn todos(
+    state: &State,
+    linked: Option<EntryId>,
+    edit_input: &NodeRef,
+    send: &Callback<Msg>,
+) -> Html {
+    let mut items_left_string = "".to_string();
+    let total = state.total();
+    if total == 1 {
+        items_left_string.push_str(" item left")
+    } else {
+        items_left_string.push_str(" items left")
+    }
+    html! {
+        <section>
+            <header class="text-center my-4">
+                <h1 class="text-6xl text-red-600">{ "todos" }</h1>
+                { input(state, send) }
+            </header>
+            <section class="my-4">
+                <label for="toggle_all" class="block w-full rounded bg-slate-300 mb-4 p-4">
+                    <input id="toggle_all" type="checkbox" checked=state.is_all_completed() onclick=send.reform(|_| Msg::ToggleAll) />
+                </label>
+                <datalist id=TAG_SUGGESTIONS>
+                    { for state.tags().into_iter().map(|tag| html! { <option value=tag /> }) }
+                </datalist>
+                <ul>
+                    { for state.visible()
+                        .into_iter()
+                        .map(|e| entry(state, e, linked, edit_input, send)) }
+                </ul>
+            </section>
+            <footer class="flex gap-3 justify-around my-4">
+                <span class="border-2 rounded p-4">
+                    <strong>{ state.total() }</strong>
+                    { items_left_string }
+                </span>
+                <ul class="flex-grow gap-3 flex flex-wrap justify-center">
+                    { for Filter::iter().map(|flt| filter(state, flt)) }
+                    <li>{ priority_filter(state, send) }</li>
+                    <li>{ sort(state, send) }</li>
+                    { tag_filter(state) }
+                </ul>
+                <button class="bg-red-300 hover:bg-red-500 hover:text-white transition-colors rounded p-4" onclick=send.reform(|_| Msg::ClearCompleted)>
+                    { format!("Clear completed ({})", state.total_completed()) }
+                </button>
+            </footer>
+        </section>
+    }
+}
+
+/// The sidebar of lists, each linking to its entries with a count of the
+/// open ones, and an input to add another. `rename_input` is attached to the
+/// input of the list being renamed.
+pub fn lists(state: &State, rename_input: &NodeRef, send: &Callback<Msg>) -> Html {
+    html! {
+        <nav class="my-4">
+            <h2 class="text-sm uppercase text-slate-500 mb-2">{ "Lists" }</h2>
+            <ul class="flex flex-col gap-1">
+                { for state.lists().iter().map(|list| list_item(state, list, rename_input, send)) }
+                <li>
+                    <input class="w-full border-b border-slate-300 p-1 focus:outline-none" placeholder="+ list"
+                           onkeypress=on_enter(send, Msg::AddList) />
+                </li>
+            </ul>
+        </nav>
+    }
+}
+
+fn list_item(state: &State, list: &List, rename_input: &NodeRef, send: &Callback<Msg>) -> Html {
+    let id = list.id;
+    if state.renaming == Some(id) {
+        // Unlike adding, a rejected name stays in the input to be fixed.
+        let on_key = send.reform(move |e: KeyboardEvent| {
+            let input = e
+                .target()
+                .and_then(|target| target.dyn_into::<HtmlInputElement>().ok());
+            match input {
+                Some(input) if e.key() == "Enter" => Msg::RenameList(id, input.value()),
+                _ => Msg::Nope,
+            }
+        });
+        return html! {
+            <li>
+                <input class="w-full border rounded p-1" title="List name" ref=rename_input.clone()
+                       value=&list.name
+                       onkeypress=on_key
+                       onkeydown=send.reform(|e: KeyboardEvent| {
+                           if e.key() == "Escape" { Msg::CancelRename } else { Msg::Nope }
+                       })
+                       onblur=send.reform(|_| Msg::CancelRename) />
+            </li>
+        };
+    }
+    let class = if id == state.list {
+        "flex items-center gap-2 rounded bg-slate-200 px-2"
+    } else {
+        "flex items-center gap-2 rounded px-2 hover:bg-slate-100"
+    };
+    let route = Route {
+        list: Some(list.name.clone()),
+        ..Route::default()
+    };
+    let open = state.in_list(id).filter(|e| !e.completed).count();
+    html! {
+        <li class=class>
+            <a class="flex-grow py-1" href=route.to_string() ondblclick=send.reform(move |_| Msg::StartRename(id))>{ &list.name }</a>
+            <span class="text-sm text-slate-500">{ open }</span>
+            <button class="text-slate-400" title="Rename list" onclick=send.reform(move |_| Msg::StartRename(id))>{ "✎" }</button>
+            <button class="text-slate-400 disabled:opacity-40" title="Delete list and its todos"
+                    disabled=state.lists().len() == 1
+                    onclick=send.reform(move |_| Msg::RemoveList(id))>{ "×" }</button>
+        </li>
+    }
+}
+
+/// What to sort the shown list by, and a button to reverse it.
+pub fn sort(state: &State, send: &Callback<Msg>) -> Html {
+    let order = state.sort();
+    let on_change = send.reform(move |data: ChangeData| match data {
+        ChangeData::Select(select) => match select.value().parse() {
+            Ok(by) => Msg::SetSort(SortOrder { by, ..order }),
+            Err(_) => Msg::Nope,
+        },
+        _ => Msg::Nope,
+    });
+    let reversed = SortOrder {
+        descending: !order.descending,
+        ..order
+    };
+    let (arrow, direction) = if order.descending {
+        ("↓", "Descending")
+    } else {
+        ("↑", "Ascending")
+    };
+    html! {
+        <div class="h-full flex gap-1">
+            <label class="flex items-center gap-2 text-slate-500">
+                { "Sort by" }
+                <select class="h-full border-2 rounded p-4 text-black" onchange=on_change>
+                    { for Sort::iter().map(|sort| html! {
+                        <option value=sort.to_string() selected=sort == order.by>{ sort.label() }</option>
+                    }) }
+                </select>
+            </label>
+            <button class="border-2 rounded px-4" title=direction aria-label=direction
+                    onclick=send.reform(move |_| Msg::SetSort(reversed))>{ arrow }</button>
+        </div>
+    }
+}
+
+/// Narrows any filter down to one priority.
+pub fn priority_filter(state: &State, send: &Callback<Msg>) -> Html {
+    let on_change = send.reform(|data: ChangeData| match data {
+        ChangeData::Select(select) => Msg::SetPriorityFilter(select.value().parse().ok()),
+        _ => Msg::Nope,
+    });
+    html! {
+        <select class="h-full border-2 rounded p-4" title="Priority filter" onchange=on_change>
+            <option value="" selected=state.priority_filter.is_none()>{ "Any priority" }</option>
+            { for Priority::iter().map(|priority| html! {
+                <option value=priority.to_string() selected=state.priority_filter == Some(priority)>
+                    { match priority {
+                        Priority::None => "No priority".to_string(),
+                        _ => format!("{} priority", priority.label()),
+                    } }
+                </option>
+            }) }
+        </select>
+    }
+}
+
+pub fn filter(state: &State, filter: Filter) -> Html {
+    let flt = filter.clone();
+    // Switching filters keeps the tag filter.
+    let route = Route {
+        filter: flt.clone(),
+        ..Route::of(state)
+    };
+
+    html! {
+        <li class="p-4 border-2 rounded">
+            <a class=if state.filter == flt { "selected" } else { "not-selected" }
+               href=route.to_string()>
+                { filter }
+            </a>
+        </li>
+    }
+}
+
+/// The tag currently filtered by, with a link that stops filtering by it.
+pub fn tag_filter(state: &State) -> Html {
+    let tag = match &state.tag_filter {
+        Some(tag) => tag,
+        None => return html! {},
+    };
+    let clear = Route {
+        tag: None,
+        ..Route::of(state)
+    };
+    html! {
+        <li class="flex">
+            <a class="flex items-center gap-2 rounded-full bg-slate-200 px-4" href=clear.to_string() title="Show all tags">
+                { format!("#{}", tag) }
+                <span aria-hidden="true">{ "×" }</span>
+            </a>
+        </li>
+    }
+}
+
+pub fn input(state: &State, send: &Callback<Msg>) -> Html {
+    let parsed = QuickAdd::parse(&state.value, &state.now);
+    html! {
+        <>
+            // You can use standard Rust comments. One line:
+            // <li></li>
+            <input id=NEW_TODO_ID
+                   class="p-4 w-full mt-4 border-0 border-b-2 border-slate-500 focus:border-slate-800 focus:outline-none"
+                   placeholder="What needs to be done?"
+                   value=&state.value
+                   oninput=send.reform(|e: InputData| Msg::Update(e.value))
+                   onkeypress=send.reform(|e: KeyboardEvent| {
+                       if e.key() == "Enter" { Msg::Add } else { Msg::Nope }
+                   }) />
+            /* Or multiline:
+            <ul>
+                <li></li>
+            </ul>
+            */
+            { feedback(state, &parsed.description, state.value_error.as_ref(), None) }
+            { preview(&parsed) }
+        </>
+    }
+}
+
+/// What the new todo input will create, once it has more than a
+/// description.
+fn preview(parsed: &QuickAdd) -> Html {
+    if !parsed.has_fields() {
+        return html! {};
+    }
+    let due = parsed.due.map(|due| match due.time {
+        Some(time) => format!("Due {} {}", due.date, time),
+        None => format!("Due {}", due.date),
+    });
+    let priority = parsed
+        .priority
+        .map(|priority| format!("{} priority", priority.label()));
+    let context = parsed
+        .context
+        .as_ref()
+        .map(|context| format!("@{}", context));
+    html! {
+        <ul class="flex flex-wrap gap-2 text-sm text-left text-slate-600 mt-1" aria-live="polite">
+            <li class="font-semibold">{ &parsed.description }</li>
+            { for due.into_iter().map(|due| html! { <li>{ due }</li> }) }
+            { for priority.into_iter().map(|priority| html! { <li>{ priority }</li> }) }
+            { for parsed.tags.iter().map(|tag| html! {
+                <li class="rounded-full bg-slate-200 px-2">{ format!("#{}", tag) }</li>
+            }) }
+            { for context.into_iter().map(|context| html! {
+                <li class="rounded-full bg-sky-100 px-2">{ context }</li>
+            }) }
+        </ul>
+    }
+}
+
+/// The reason a description was rejected or, failing that, a warning if
+/// another entry already has it.
+fn feedback(state: &State, value: &str, error: Option<&Invalid>, except: Option<EntryId>) -> Html {
+    if let Some(error) = error {
+        html! { <p class="text-sm text-left text-red-600 mt-1">{ error }</p> }
+    } else if let Some(duplicate) = validation::duplicate(state.entries(), value, except) {
+        html! {
+            <p class="text-sm text-left text-amber-600 mt-1">
+                { format!("\u{201c}{}\u{201d} is already on your list.", duplicate.description) }
+            </p>
+        }
+    } else {
+        html! {}
+    }
+}
+
+pub fn entry(
+    state: &State,
+    entry: &Entry,
+    linked: Option<EntryId>,
+    edit_input: &NodeRef,
+    send: &Callback<Msg>,
+) -> Html {
+    let id = entry.id;
+    let editing = state.editing == Some(id);
+    let mut label_class = "".to_string();
+    if editing {
+        label_class.push_str(" hidden");
+    }
+    if entry.completed {
+        label_class.push_str(" inline");
+    }
+
+    let mut item_class = "p-4 pr-0 my-2 border-b-2 border-slate-200 last:border-0".to_string();
+    if linked == Some(id) {
+        item_class.push_str(" rounded ring-2 ring-amber-400");
+    }
+    match urgency(state, entry) {
+        Some(Urgency::Overdue) => item_class.push_str(" bg-red-50"),
+        Some(Urgency::Today) => item_class.push_str(" bg-amber-50"),
+        _ => {}
+    }
+    if state.dragging == Some(id) {
+        item_class.push_str(" opacity-50");
+    }
+    // Entries can only be dragged while they are shown in list order.
+    let draggable = if state.sort().by == Sort::Manual {
+        "true"
+    } else {
+        "false"
+    };
+    let on_drag_start = send.reform(move |e: DragEvent| {
+        // Firefox only starts dragging once there is some data.
+        if let Some(data) = e.data_transfer() {
+            data.set_effect_allowed("move");
+            let _ = data.set_data("text/plain", &id.to_string());
+        }
+        Msg::DragStart(id)
+    });
+    let on_drop = send.reform(move |e: DragEvent| {
+        e.prevent_default();
+        Msg::Drop(id)
+    });
+    let permalink = Route {
+        entry: Some(id),
+        ..Route::of(state)
+    };
+
+    html! {
+        <li id=entry_element_id(id) class=item_class tabindex="0" onkeydown=entry_keys(id, send)
+            draggable=draggable ondragstart=on_drag_start ondragend=send.reform(|_| Msg::DragEnd)
+            ondragover=Callback::from(|e: DragEvent| e.prevent_default()) ondrop=on_drop>
+            <div class="flex items-center justify-between">
+            <div class="flex gap-6">
+                <input type="checkbox" checked=entry.completed onclick=send.reform(move |_| Msg::Toggle(id)) />
+                { priority_marker(entry.priority) }
+                <label class=label_class title=timestamps(state, entry) ondblclick=send.reform(move |_| Msg::ToggleEdit(id))>{ &entry.description }</label>
+                { entry_edit_input(state, entry, editing, edit_input, send) }
+                <a class="text-slate-400" href=permalink.to_string() title="Link to this todo">{ "#" }</a>
+                { tags(state, entry, send) }
+                { context(entry, send) }
+                { progress(state, entry, send) }
+            </div>
+                { due(state, entry, send) }
+                { priority(entry, send) }
+                { move_to(state, entry, send) }
+                <button class="bg-red-300 hover:bg-red-500 hover:text-white transition-colors rounded p-4 ml-auto" onclick=send.reform(move |_| Msg::Remove(id))>{"Remove"}</button>
+            </div>
+            { subtasks(state, entry, send) }
+        </li>
+    }
+}
+
+/// When the entry was added, last edited and completed, as far as that is
+/// known, for the tooltip of its description.
+fn timestamps(state: &State, entry: &Entry) -> String {
+    let mut lines = Vec::new();
+    if let Some(created) = entry.created_at {
+        lines.push(format!("Created {}", state.now.ago(created)));
+    }
+    if let Some(edited) = entry.edited_at {
+        lines.push(format!("Edited {}", state.now.ago(edited)));
+    }
+    if let Some(completed) = entry.completed_at {
+        lines.push(format!("Completed {}", state.now.ago(completed)));
+    }
+    lines.join("\n")
+}
+
+/// How soon an open entry is due.
+fn urgency(state: &State, entry: &Entry) -> Option<Urgency> {
+    entry
+        .due
+        .filter(|_| !entry.completed)
+        .map(|due| due.urgency(&state.now))
+}
+
+/// Date and time pickers for the due date, with a note if it is close.
+fn due(state: &State, entry: &Entry, send: &Callback<Msg>) -> Html {
+    let id = entry.id;
+    let date = entry.due.map(|due| due.date);
+    let time = entry.due.and_then(|due| due.time);
+    let on_date = send.reform(move |data: ChangeData| match data {
+        ChangeData::Value(value) if value.is_empty() => Msg::SetDue(id, None),
+        ChangeData::Value(value) => match value.parse() {
+            Ok(date) => Msg::SetDue(id, Some(Due { date, time })),
+            Err(_) => Msg::Nope,
+        },
+        _ => Msg::Nope,
+    });
+    let on_time = send.reform(move |data: ChangeData| match (date, data) {
+        (Some(date), ChangeData::Value(value)) => Msg::SetDue(
+            id,
+            Some(Due {
+                date,
+                time: value.parse().ok(),
+            }),
+        ),
+        _ => Msg::Nope,
+    });
+    let (note, note_class) = match urgency(state, entry) {
+        Some(Urgency::Overdue) => ("Overdue", "text-red-600"),
+        Some(Urgency::Today) => ("Due today", "text-amber-600"),
+        _ => ("", ""),
+    };
+    html! {
+        <div class="flex items-center gap-2 text-sm ml-auto mr-4">
+            <span class=note_class>{ note }</span>
+            <input type="date" title="Due date" class="border rounded p-1"
+                   value=date.map(|date| date.to_string()).unwrap_or_default()
+                   onchange=on_date />
+            <input type="time" title="Due time" class="border rounded p-1"
+                   disabled=date.is_none()
+                   value=time.map(|time| time.to_string()).unwrap_or_default()
+                   onchange=on_time />
+        </div>
+    }
+}
+
+fn priority_marker(priority: Priority) -> Html {
+    let color = match priority {
+        Priority::High => "bg-red-500",
+        Priority::Medium => "bg-amber-400",
+        Priority::Low => "bg-sky-400",
+        Priority::None => "bg-transparent",
+    };
+    html! {
+        <span class=format!("self-center w-3 h-3 rounded-full {}", color) title=format!("Priority: {}", priority.label())></span>
+    }
+}
+
+fn priority(entry: &Entry, send: &Callback<Msg>) -> Html {
+    let id = entry.id;
+    let on_change = send.reform(move |data: ChangeData| match data {
+        ChangeData::Select(select) => match select.value().parse() {
+            Ok(priority) => Msg::SetPriority(id, priority),
+            Err(_) => Msg::Nope,
+        },
+        _ => Msg::Nope,
+    });
+    html! {
+        <select class="border rounded text-sm p-1 mr-4" title="Priority" onchange=on_change>
+            { for Priority::iter().map(|priority| html! {
+                <option value=priority.to_string() selected=priority == entry.priority>{ priority.label() }</option>
+            }) }
+        </select>
+    }
+}
+
+/// Moves the entry to another list, if there is one.
+fn move_to(state: &State, entry: &Entry, send: &Callback<Msg>) -> Html {
+    if state.lists().len() < 2 {
+        return html! {};
+    }
+    let id = entry.id;
+    let on_change = send.reform(move |data: ChangeData| match data {
+        ChangeData::Select(select) => match select.value().parse() {
+            Ok(list) => Msg::MoveEntry(id, list),
+            Err(_) => Msg::Nope,
+        },
+        _ => Msg::Nope,
+    });
+    html! {
+        <select class="border rounded text-sm p-1 mr-4" title="List" onchange=on_change>
+            { for state.lists().iter().map(|list| html! {
+                <option value=list.id.to_string() selected=list.id == entry.list>{ &list.name }</option>
+            }) }
+        </select>
+    }
+}
+
+/// Shortcuts for while the entry itself, not one of its inputs, has the
+/// focus: 0 (none) to 3 (high) set the priority, and Alt+Up and Alt+Down
+/// move the entry past its visible neighbour.
+fn entry_keys(id: EntryId, send: &Callback<Msg>) -> Callback<KeyboardEvent> {
+    send.reform(move |e: KeyboardEvent| {
+        if e.target() != e.current_target() || e.ctrl_key() || e.meta_key() {
+            return Msg::Nope;
+        }
+        if e.alt_key() {
+            let after = match e.key().as_str() {
+                "ArrowUp" => false,
+                "ArrowDown" => true,
+                _ => return Msg::Nope,
+            };
+            e.prevent_default();
+            return Msg::Reorder(id, after);
+        }
+        let priority = match e.key().as_str() {
+            "0" => Priority::None,
+            "1" => Priority::Low,
+            "2" => Priority::Medium,
+            "3" => Priority::High,
+            _ => return Msg::Nope,
+        };
+        Msg::SetPriority(id, priority)
+    })
+}
+
+/// The entry's tags, each linking to the list filtered by it, and an input
+/// to add more that suggests the tags already in use.
+fn tags(state: &State, entry: &Entry, send: &Callback<Msg>) -> Html {
+    let id = entry.id;
+    let on_add = on_enter(send, move |tag| Msg::AddTag(id, tag));
+    html! {
+        <ul class="flex flex-wrap items-center gap-1 text-sm">
+            { for entry.tags.iter().map(|tag| {
+                let route = Route {
+                    tag: Some(tag.clone()),
+                    ..Route::of(state)
+                };
+                let removed = tag.clone();
+                html! {
+                    <li class="flex gap-1 rounded-full bg-slate-200 px-2">
+                        <a href=route.to_string()>{ format!("#{}", tag) }</a>
+                        <button title="Remove tag" onclick=send.reform(move |_| Msg::RemoveTag(id, removed.clone()))>{ "×" }</button>
+                    </li>
+                }
+            }) }
+            <li>
+                <input class="w-20 border-b border-slate-300 focus:outline-none" placeholder="+ tag"
+                       list=TAG_SUGGESTIONS onkeypress=on_add />
+            </li>
+        </ul>
+    }
+}
+
+/// Sends what was typed into the input when Enter is pressed, and clears it.
+fn on_enter(
+    send: &Callback<Msg>,
+    msg: impl Fn(String) -> Msg + 'static,
+) -> Callback<KeyboardEvent> {
+    send.reform(move |e: KeyboardEvent| {
+        let input = e
+            .target()
+            .and_then(|target| target.dyn_into::<HtmlInputElement>().ok());
+        match input {
+            Some(input) if e.key() == "Enter" => {
+                let value = input.value();
+                input.set_value("");
+                msg(value)
+            }
+            _ => Msg::Nope,
+        }
+    })
+}
+
+/// How many subtasks are done, on a button that hides or shows them.
+fn progress(state: &State, entry: &Entry, send: &Callback<Msg>) -> Html {
+    let id = entry.id;
+    let (done, total) = match entry.progress() {
+        Some(progress) => progress,
+        None => return html! {},
+    };
+    let collapsed = state.collapsed.contains(&id);
+    let (arrow, title) = if collapsed {
+        ("▸", "Show subtasks")
+    } else {
+        ("▾", "Hide subtasks")
+    };
+    html! {
+        <button class="self-center text-sm text-slate-500" title=title
+                aria-expanded=(!collapsed).to_string()
+                onclick=send.reform(move |_| Msg::ToggleCollapsed(id))>
+            { format!("{} {}/{}", arrow, done, total) }
+        </button>
+    }
+}
+
+/// The checklist under an entry, with an input to add to it, unless it is
+/// collapsed.
+fn subtasks(state: &State, entry: &Entry, send: &Callback<Msg>) -> Html {
+    let id = entry.id;
+    if state.collapsed.contains(&id) && !entry.subtasks.is_empty() {
+        return html! {};
+    }
+    let auto_complete = if entry.subtasks.is_empty() {
+        html! {}
+    } else {
+        let enabled = entry.auto_complete;
+        html! {
+            <label class="flex items-center gap-2 mt-1 text-slate-500">
+                <input type="checkbox" checked=enabled
+                       onclick=send.reform(move |_| Msg::SetAutoComplete(id, !enabled)) />
+                { "Complete the todo when all subtasks are" }
+            </label>
+        }
+    };
+    html! {
+        <div class="ml-12 mt-2 text-sm">
+            <ul>
+                { for entry.subtasks.iter().map(|subtask| {
+                    let subtask_id = subtask.id;
+                    let label_class = if subtask.completed { "line-through text-slate-400" } else { "" };
+                    html! {
+                        <li class="flex items-center gap-2">
+                            <input type="checkbox" checked=subtask.completed
+                                   onclick=send.reform(move |_| Msg::ToggleSubtask(id, subtask_id)) />
+                            <label class=label_class>{ &subtask.description }</label>
+                            <button title="Remove subtask" onclick=send.reform(move |_| Msg::RemoveSubtask(id, subtask_id))>{ "×" }</button>
+                        </li>
+                    }
+                }) }
+                <li>
+                    <input class="w-48 border-b border-slate-300 focus:outline-none" placeholder="+ subtask"
+                           onkeypress=on_enter(send, move |description| Msg::AddSubtask(id, description)) />
+                </li>
+            </ul>
+            { auto_complete }
+        </div>
+    }
+}
+
+fn context(entry: &Entry, send: &Callback<Msg>) -> Html {
+    let id = entry.id;
+    match &entry.context {
+        Some(context) => html! {
+            <span class="flex gap-1 self-center rounded-full bg-sky-100 text-sm px-2">
+                { format!("@{}", context) }
+                <button title="Remove context" onclick=send.reform(move |_| Msg::SetContext(id, None))>{ "×" }</button>
+            </span>
+        },
+        None => html! {},
+    }
+}
+
+fn entry_edit_input(
+    state: &State,
+    entry: &Entry,
+    editing: bool,
+    edit_input: &NodeRef,
+    send: &Callback<Msg>,
+) -> Html {
+    let id = entry.id;
+    if editing {
+        html! {
+            <div class="flex flex-col">
+                <input
+                    type="text"
+                    ref=edit_input.clone()
+                    value=state.edit_value
+                    oninput=send.reform(move |e: InputData| Msg::UpdateEdit(e.value))
+                    onblur=send.reform(move |_| Msg::Edit(id))
+                    onkeypress=send.reform(move |e: KeyboardEvent| {
+                        if e.key() == "Enter" { Msg::Edit(id) } else { Msg::Nope }
+                    })
+                    // Escape doesn't cause a keypress.
+                    onkeydown=send.reform(move |e: KeyboardEvent| {
+                        if e.key() == "Escape" { Msg::CancelEdit(id) } else { Msg::Nope }
+                    }) />
+                { feedback(state, &state.edit_value, state.edit_error.as_ref(), Some(id)) }
+            </div>
+        }
+    } else {
+        html! { <input type="hidden" /> }
+    }
+}
+
+/// The DOM id of the new todo input.
+pub const NEW_TODO_ID: &str = "new-todo";
+
+/// The DOM id of an entry's list item, which deep links scroll to.
+pub fn entry_element_id(id: EntryId) -> String {
+    format!("entry-{}", id)
+}
@@ -0,0 +1,49 @@
+#![cfg(feature = "devtools")]
+
+use serde_json::json;
+use yew_wasm_pack_tw_template::devtools::{diff, Command, Devtools};
+
+#[test]
+fn diffs_changed_fields() {
+    let before = json!({ "filter": "All", "value": "Buy" });
+    let after = json!({ "filter": "All", "value": "Buy milk" });
+    assert_eq!(diff(&before, &after), [r#"value: "Buy" → "Buy milk""#]);
+}
+
+#[test]
+fn matches_entries_by_id() {
+    let before = json!({ "entries": [
+        { "id": 1, "completed": false },
+        { "id": 2, "completed": false },
+    ] });
+    let after = json!({ "entries": [{ "id": 2, "completed": true }] });
+    assert_eq!(
+        diff(&before, &after),
+        [
+            r#"entries[id=1]: {"completed":false,"id":1} → (none)"#,
+            "entries[id=2].completed: false → true",
+        ]
+    );
+}
+
+#[test]
+fn steps_through_recorded_states() {
+    let mut devtools = Devtools::default();
+    for step in 0..3 {
+        devtools.record(format!("Step{}", step), json!(step), json!(step + 1));
+    }
+    assert!(!devtools.shows_past());
+    assert_eq!(devtools.handle(Command::Jump(0)), Some(&json!(1)));
+    assert!(devtools.shows_past());
+    assert_eq!(devtools.handle(Command::Forward), Some(&json!(2)));
+    assert_eq!(devtools.handle(Command::Back), Some(&json!(1)));
+    assert_eq!(devtools.handle(Command::Resume), Some(&json!(3)));
+    assert!(!devtools.shows_past());
+
+    // Recording after jumping back drops the later frames.
+    devtools.handle(Command::Jump(0));
+    devtools.record("Other".into(), json!(1), json!(10));
+    assert!(!devtools.shows_past());
+    assert_eq!(devtools.handle(Command::Resume), Some(&json!(10)));
+    assert_eq!(devtools.handle(Command::Back), Some(&json!(1)));
+}
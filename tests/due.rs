@@ -0,0 +1,98 @@
+use yew_wasm_pack_tw_template::due::{Date, Due, Now, Time, Urgency};
+
+fn due(s: &str) -> Due {
+    s.parse().unwrap()
+}
+
+#[test]
+fn parses_what_date_and_time_inputs_produce() {
+    let date = Date {
+        year: 2024,
+        month: 2,
+        day: 29,
+    };
+    assert_eq!(due("2024-02-29"), Due { date, time: None });
+    assert_eq!(
+        due("2024-02-29T07:05"),
+        Due {
+            date,
+            time: Some(Time { hour: 7, minute: 5 })
+        }
+    );
+    assert_eq!(due("2024-02-29T07:05:30"), due("2024-02-29T07:05"));
+    assert_eq!(due("2024-02-29T07:05").to_string(), "2024-02-29T07:05");
+}
+
+#[test]
+fn rejects_impossible_dates() {
+    for invalid in [
+        "",
+        "2024-13-01",
+        "2023-02-29",
+        "2024-04-31",
+        "2024-05-01T24:00",
+        "today",
+    ] {
+        assert!(
+            invalid.parse::<Due>().is_err(),
+            "{:?} was accepted",
+            invalid
+        );
+    }
+}
+
+#[test]
+fn orders_chronologically() {
+    assert!(due("2024-05-01") < due("2024-05-01T00:00"));
+    assert!(due("2024-05-01T23:59") < due("2024-05-02"));
+    assert!(due("2023-12-31") < due("2024-01-01"));
+}
+
+#[test]
+fn tells_how_urgent_it_is() {
+    let now = Now {
+        date: "2024-05-01".parse().unwrap(),
+        time: "12:00".parse().unwrap(),
+        ..Now::default()
+    };
+    assert_eq!(due("2024-04-30T23:00").urgency(&now), Urgency::Overdue);
+    assert_eq!(due("2024-05-01T11:59").urgency(&now), Urgency::Overdue);
+    assert_eq!(due("2024-05-01T12:00").urgency(&now), Urgency::Today);
+    assert_eq!(due("2024-05-01").urgency(&now), Urgency::Today);
+    assert_eq!(due("2024-05-02T00:00").urgency(&now), Urgency::Upcoming);
+}
+
+#[test]
+fn counts_days_across_months_and_years() {
+    let date = |s: &str| s.parse::<Date>().unwrap();
+    assert_eq!(date("2024-02-28").add_days(1), date("2024-02-29"));
+    assert_eq!(date("2023-02-28").add_days(1), date("2023-03-01"));
+    assert_eq!(date("2024-12-30").add_days(7), date("2025-01-06"));
+    assert_eq!(date("2024-05-01").add_days(0), date("2024-05-01"));
+}
+
+#[test]
+fn knows_the_weekday() {
+    let weekday = |s: &str| s.parse::<Date>().unwrap().weekday();
+    assert_eq!(weekday("2024-05-01"), 2);
+    assert_eq!(weekday("2000-02-29"), 1);
+    assert_eq!(weekday("2024-12-31"), 1);
+    assert_eq!(weekday("1999-01-01"), 4);
+    assert_eq!(weekday("2024-05-05"), 6);
+}
+
+#[test]
+fn tells_how_long_ago() {
+    let now = Now {
+        timestamp: 1_714_564_800_000.0,
+        ..Now::default()
+    };
+    let before = |minutes: f64| now.timestamp - minutes * 60_000.0;
+    assert_eq!(now.ago(before(0.5)), "just now");
+    assert_eq!(now.ago(before(-5.0)), "just now");
+    assert_eq!(now.ago(before(5.0)), "5m ago");
+    assert_eq!(now.ago(before(150.0)), "2h ago");
+    assert_eq!(now.ago(before(3.0 * 24.0 * 60.0)), "3d ago");
+    assert_eq!(now.ago(before(15.0 * 24.0 * 60.0)), "2w ago");
+    assert_eq!(now.ago(before(800.0 * 24.0 * 60.0)), "2y ago");
+}
@@ -0,0 +1,108 @@
+use yew_wasm_pack_tw_template::events::{Action, EventLog};
+use yew_wasm_pack_tw_template::model::{Filter, State, DEFAULT_LIST};
+use yew_wasm_pack_tw_template::schema::Persisted;
+
+fn actions() -> Vec<Action> {
+    vec![
+        Action::Add {
+            id: 1,
+            description: "Buy milk".into(),
+            list: DEFAULT_LIST,
+        },
+        Action::Add {
+            id: 2,
+            description: "Walk the dog".into(),
+            list: DEFAULT_LIST,
+        },
+        Action::Toggle { id: 1 },
+        Action::Edit {
+            id: 2,
+            description: "Walk the cat".into(),
+        },
+        Action::ToggleAll {
+            filter: Filter::Active,
+            completed: true,
+        },
+        Action::Toggle { id: 1 },
+        Action::ClearCompleted { list: None },
+    ]
+}
+
+#[test]
+fn replay_matches_applying_directly() {
+    let mut state = State::new(Persisted::default());
+    let mut log = EventLog::new(state.to_persisted());
+    for (at, action) in actions().into_iter().enumerate() {
+        state.apply(at as f64, &action);
+        log.record(at as f64, action);
+    }
+    assert_eq!(log.replay().to_persisted(), state.to_persisted());
+    let descriptions: Vec<_> = state.entries().iter().map(|e| &e.description).collect();
+    assert_eq!(descriptions, ["Buy milk"]);
+}
+
+#[test]
+fn compaction_keeps_replay_intact() {
+    let mut state = State::new(Persisted::default());
+    let mut log = EventLog::new(state.to_persisted());
+    for round in 0..200 {
+        for action in actions() {
+            let action = match action {
+                Action::Add {
+                    id,
+                    description,
+                    list,
+                } => Action::Add {
+                    id: id + round * 2,
+                    description,
+                    list,
+                },
+                other => other,
+            };
+            state.apply(0.0, &action);
+            log.record(0.0, action);
+        }
+    }
+    assert!(log.events.len() < 200 * actions().len());
+    assert_eq!(
+        log.folded as usize + log.events.len(),
+        200 * actions().len()
+    );
+    assert_eq!(log.replay().to_persisted(), state.to_persisted());
+}
+
+#[test]
+fn round_trips_through_json() {
+    let mut log = EventLog::new(Persisted::default());
+    for action in actions() {
+        log.record(1_700_000_000_000.0, action);
+    }
+    let decoded = EventLog::decode(&log.encode()).unwrap();
+    assert_eq!(decoded.events, log.events);
+    assert_eq!(decoded.replay().to_persisted(), log.replay().to_persisted());
+}
+
+#[test]
+fn migrates_logs_from_before_snapshots_were_versioned() {
+    let log = EventLog::decode(include_str!("fixtures/events/unversioned.json")).unwrap();
+    assert_eq!(log.snapshot.entries[0].list, DEFAULT_LIST);
+    assert_eq!(log.snapshot.lists.len(), 1);
+    let state = log.replay();
+    let descriptions: Vec<_> = state.entries().iter().map(|e| &e.description).collect();
+    assert_eq!(descriptions, ["Walk the dog"]);
+}
+
+#[test]
+fn reads_actions_from_before_there_were_lists() {
+    let add: Action = serde_json::from_str(r#"{"type":"add","id":1,"description":"x"}"#).unwrap();
+    assert_eq!(
+        add,
+        Action::Add {
+            id: 1,
+            description: "x".into(),
+            list: DEFAULT_LIST,
+        }
+    );
+    let clear: Action = serde_json::from_str(r#"{"type":"clear_completed"}"#).unwrap();
+    assert_eq!(clear, Action::ClearCompleted { list: None });
+}
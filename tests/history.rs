@@ -0,0 +1,169 @@
+use yew_wasm_pack_tw_template::events::Action;
+use yew_wasm_pack_tw_template::history::{History, Snapshot, Undoable};
+use yew_wasm_pack_tw_template::model::{State, DEFAULT_LIST};
+use yew_wasm_pack_tw_template::schema::Persisted;
+
+/// A number that undo steps add to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct N(i32);
+
+impl Undoable for N {
+    type Step = i32;
+
+    fn step_to(&self, to: &N) -> i32 {
+        to.0 - self.0
+    }
+
+    fn apply(&self, step: &i32) -> N {
+        N(self.0 + step)
+    }
+}
+
+#[test]
+fn undoes_and_redoes() {
+    let mut history = History::default();
+    history.record(&N(0), &N(1), None);
+    history.record(&N(1), &N(2), None);
+    assert_eq!(history.undo(&N(2)), Some(N(1)));
+    assert_eq!(history.undo(&N(1)), Some(N(0)));
+    assert_eq!(history.undo(&N(0)), None);
+    assert_eq!(history.redo(&N(0)), Some(N(1)));
+    assert_eq!(history.redo(&N(1)), Some(N(2)));
+    assert!(!history.can_redo());
+}
+
+#[test]
+fn new_changes_clear_redo() {
+    let mut history = History::default();
+    history.record(&N(0), &N(1), None);
+    assert_eq!(history.undo(&N(1)), Some(N(0)));
+    history.record(&N(0), &N(5), None);
+    assert!(!history.can_redo());
+}
+
+#[test]
+fn coalesces_changes_in_the_same_group() {
+    let mut history = History::default();
+    assert!(history.record(&N(0), &N(1), Some("draft")));
+    assert!(!history.record(&N(1), &N(2), Some("draft")));
+    assert!(!history.record(&N(2), &N(3), Some("draft")));
+    assert_eq!(history.undo(&N(3)), Some(N(0)));
+    assert!(!history.can_undo());
+}
+
+#[test]
+fn forgets_the_oldest_steps() {
+    let mut history = History::default();
+    for step in 0..100 {
+        history.record(&N(step), &N(step + 1), None);
+    }
+    let mut current = N(100);
+    let mut undone = 0;
+    while let Some(previous) = history.undo(&current) {
+        current = previous;
+        undone += 1;
+    }
+    assert!(undone < 100);
+    assert!(current.0 > 0);
+}
+
+fn state(descriptions: &[&str]) -> State {
+    let mut state = State::new(Persisted::default());
+    for (id, description) in descriptions.iter().enumerate() {
+        let action = Action::Add {
+            id: id as u64 + 1,
+            description: (*description).into(),
+            list: DEFAULT_LIST,
+        };
+        state.apply(0.0, &action);
+    }
+    state
+}
+
+fn descriptions(snapshot: &Snapshot) -> Vec<&str> {
+    snapshot
+        .entries
+        .iter()
+        .map(|e| e.description.as_str())
+        .collect()
+}
+
+/// Applies `action` to `state`, recording it in `history`.
+fn change(history: &mut History<Snapshot>, state: &mut State, action: Action) {
+    let before = Snapshot::of(state);
+    state.apply(1.0, &action);
+    history.record(&before, &Snapshot::of(state), None);
+}
+
+#[test]
+fn undoing_a_removal_puts_the_entry_back_in_place() {
+    let mut state = state(&["Buy milk", "Walk the dog", "Call mum"]);
+    let mut history = History::default();
+    change(&mut history, &mut state, Action::Remove { id: 2 });
+    let restored = history.undo(&Snapshot::of(&state)).unwrap();
+    assert_eq!(
+        descriptions(&restored),
+        ["Buy milk", "Walk the dog", "Call mum"]
+    );
+    let removed = history.redo(&restored).unwrap();
+    assert_eq!(descriptions(&removed), ["Buy milk", "Call mum"]);
+}
+
+#[test]
+fn undoes_a_reorder() {
+    let mut state = state(&["Buy milk", "Walk the dog", "Call mum"]);
+    let before = Snapshot::of(&state);
+    let mut history = History::default();
+    let reorder = Action::Reorder {
+        id: 1,
+        target: 3,
+        after: true,
+    };
+    change(&mut history, &mut state, reorder);
+    let after = Snapshot::of(&state);
+    assert_eq!(
+        descriptions(&after),
+        ["Walk the dog", "Call mum", "Buy milk"]
+    );
+    assert_eq!(history.undo(&after), Some(before));
+}
+
+#[test]
+fn steps_only_hold_the_entries_they_change() {
+    let mut state = state(&["Buy milk", "Walk the dog", "Call mum"]);
+    let before = Snapshot::of(&state);
+    state.apply(1.0, &Action::Toggle { id: 2 });
+    let mut history = History::default();
+    history.record(&before, &Snapshot::of(&state), None);
+    let encoded = history.encode();
+    assert!(encoded.contains("Walk the dog"));
+    assert!(!encoded.contains("Buy milk"));
+    assert!(!encoded.contains("Call mum"));
+}
+
+#[test]
+fn round_trips_through_encode() {
+    let mut state = state(&["Buy milk", "Walk the dog"]);
+    let mut history = History::default();
+    change(&mut history, &mut state, Action::Toggle { id: 1 });
+    change(
+        &mut history,
+        &mut state,
+        Action::Edit {
+            id: 2,
+            description: "Walk the cat".into(),
+        },
+    );
+    let current = Snapshot::of(&state);
+    let mut decoded = History::decode(&history.encode()).unwrap();
+    assert_eq!(decoded.undo(&current), history.undo(&current));
+}
+
+#[test]
+fn migrates_steps_written_by_older_versions() {
+    let raw = include_str!("fixtures/history/v1.json");
+    let mut history = History::decode(raw).unwrap();
+    let restored = history.undo(&Snapshot::default()).unwrap();
+    assert_eq!(descriptions(&restored), ["Buy milk"]);
+    assert_eq!(restored.entries[0].id, 3);
+}
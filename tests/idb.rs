@@ -0,0 +1,215 @@
+//! Browser tests for the IndexedDB store. Run them with
+//! `wasm-pack test --headless --firefox` (or `--chrome`).
+
+#![cfg(target_arch = "wasm32")]
+
+use js_sys::{Array, Promise};
+use serde_json::{json, Value};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+use web_sys::{Event, IdbDatabase, IdbFactory, IdbOpenDbRequest, IdbTransactionMode};
+use yew::Callback;
+use yew_wasm_pack_tw_template::events::{Action, EventLog};
+use yew_wasm_pack_tw_template::model::{State, DEFAULT_LIST};
+use yew_wasm_pack_tw_template::schema::Persisted;
+use yew_wasm_pack_tw_template::storage::{IdbStore, Stored};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+const DB_NAME: &str = "yew.todomvc";
+
+fn factory() -> IdbFactory {
+    web_sys::window().unwrap().indexed_db().unwrap().unwrap()
+}
+
+/// Waits for `request` to succeed, failing if it errors or is blocked by
+/// a connection that stays open.
+async fn settled(request: &IdbOpenDbRequest) -> Result<(), String> {
+    let done = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+        request.set_onblocked(Some(&reject));
+    });
+    let done = JsFuture::from(done).await;
+    request.set_onblocked(None);
+    done.map(drop).map_err(|_| "blocked or failed".to_string())
+}
+
+/// Deletes the database, failing if a connection to it isn't closed.
+async fn delete_database() {
+    let request = factory().delete_database(DB_NAME).unwrap();
+    settled(&request).await.expect("database deleted");
+}
+
+/// A state with a todo for each of `descriptions`, in that order.
+fn state(descriptions: &[&str]) -> State {
+    let mut state = State::new(Persisted::default());
+    for description in descriptions {
+        let add = Action::Add {
+            id: state.allocate_id(),
+            description: (*description).into(),
+            list: DEFAULT_LIST,
+        };
+        state.apply(0.0, &add);
+    }
+    state
+}
+
+fn descriptions(stored: &Stored) -> Vec<&str> {
+    stored
+        .entries
+        .entries
+        .iter()
+        .map(|e| e.description.as_str())
+        .collect()
+}
+
+/// Opens the database again, as a reload would. Writes handed to a sync
+/// before are done by then, as its transaction started first.
+async fn reopen() -> (IdbStore, Stored) {
+    let (store, stored) = IdbStore::open().await.unwrap();
+    (store, stored.expect("something stored"))
+}
+
+/// Sets the database up as the first version of the store left it: entry
+/// records that hold their position, and no event store.
+async fn create_first_version(meta: Value, entries: &[Value]) {
+    let request = factory().open_with_u32(DB_NAME, 1).unwrap();
+    let on_upgrade = Closure::once_into_js(|event: Event| {
+        let request: IdbOpenDbRequest = event.target().unwrap().unchecked_into();
+        let db: IdbDatabase = request.result().unwrap().unchecked_into();
+        db.create_object_store("entries").unwrap();
+        db.create_object_store("meta").unwrap();
+    });
+    request.set_onupgradeneeded(Some(on_upgrade.unchecked_ref()));
+    settled(&request).await.unwrap();
+    let db: IdbDatabase = request.result().unwrap().unchecked_into();
+    let names = Array::of2(&"entries".into(), &"meta".into());
+    let tx = db
+        .transaction_with_str_sequence_and_mode(&names, IdbTransactionMode::Readwrite)
+        .unwrap();
+    let store = tx.object_store("entries").unwrap();
+    for record in entries {
+        let id = record["entry"]["id"].as_f64().unwrap();
+        store
+            .put_with_key(&record.to_string().into(), &id.into())
+            .unwrap();
+    }
+    let store = tx.object_store("meta").unwrap();
+    store
+        .put_with_key(&meta.to_string().into(), &"meta".into())
+        .unwrap();
+    let done = Promise::new(&mut |resolve, reject| {
+        tx.set_oncomplete(Some(&resolve));
+        tx.set_onabort(Some(&reject));
+    });
+    JsFuture::from(done).await.unwrap();
+    db.close();
+}
+
+#[wasm_bindgen_test]
+async fn round_trips_entries_log_and_history() {
+    delete_database().await;
+    let (mut store, stored) = IdbStore::open().await.unwrap();
+    assert!(stored.is_none());
+    let mut state = state(&["Buy milk", "Walk the dog"]);
+    let mut log = EventLog::new(state.to_persisted());
+    let toggle = Action::Toggle { id: 1 };
+    state.apply(1.0, &toggle);
+    log.record(1.0, toggle);
+    store.sync(&state, &log, Some("history".into()), Callback::noop());
+
+    let (_, stored) = reopen().await;
+    assert_eq!(stored.entries, state.to_persisted());
+    let log = stored.log.expect("log stored");
+    assert_eq!(log.events.len(), 1);
+    assert_eq!(log.replay().entries(), state.entries());
+    assert_eq!(stored.history.as_deref(), Some("history"));
+}
+
+#[wasm_bindgen_test]
+async fn keeps_the_order_after_moving_and_removing() {
+    delete_database().await;
+    let (mut store, _) = IdbStore::open().await.unwrap();
+    let mut state = state(&["Buy milk", "Walk the dog", "Call mum"]);
+    let log = EventLog::new(state.to_persisted());
+    store.sync(&state, &log, None, Callback::noop());
+    let reorder = Action::Reorder {
+        id: 3,
+        target: 1,
+        after: false,
+    };
+    state.apply(1.0, &reorder);
+    state.apply(2.0, &Action::Remove { id: 2 });
+    store.sync(&state, &log, None, Callback::noop());
+
+    let (_, stored) = reopen().await;
+    assert_eq!(descriptions(&stored), ["Call mum", "Buy milk"]);
+}
+
+#[wasm_bindgen_test]
+async fn migrates_records_in_an_older_shape() {
+    delete_database().await;
+    let entry = |id: u64, description: &str, position: u64| {
+        json!({
+            "entry": { "id": id, "description": description, "completed": false, "editing": false },
+            "position": position,
+        })
+    };
+    let entries = [entry(3, "Buy milk", 1), entry(7, "Walk the dog", 0)];
+    create_first_version(json!({ "version": 1, "next_id": 9 }), &entries).await;
+
+    let (mut store, stored) = reopen().await;
+    assert_eq!(descriptions(&stored), ["Walk the dog", "Buy milk"]);
+    assert_eq!(stored.entries.next_id, 9);
+    assert!(stored
+        .entries
+        .entries
+        .iter()
+        .all(|e| e.list == DEFAULT_LIST));
+    // The next sync rewrites the records in the current shape.
+    let state = State::new(stored.entries.clone());
+    let log = EventLog::new(state.to_persisted());
+    store.sync(&state, &log, None, Callback::noop());
+    let (_, rewritten) = reopen().await;
+    assert_eq!(rewritten.entries, stored.entries);
+}
+
+#[wasm_bindgen_test]
+async fn a_sync_after_invalidating_drops_deleted_entries() {
+    delete_database().await;
+    let (mut store, _) = IdbStore::open().await.unwrap();
+    let mut state = state(&["Buy milk", "Walk the dog"]);
+    let log = EventLog::new(state.to_persisted());
+    store.sync(&state, &log, None, Callback::noop());
+    // As if the write that deleted the entry had failed and been rolled
+    // back, so the store no longer knows what is in the database.
+    state.apply(1.0, &Action::Remove { id: 2 });
+    store.invalidate();
+    store.sync(&state, &log, None, Callback::noop());
+
+    let (_, stored) = reopen().await;
+    assert_eq!(descriptions(&stored), ["Buy milk"]);
+}
+
+#[wasm_bindgen_test]
+async fn gives_up_when_an_older_version_is_open_elsewhere() {
+    delete_database().await;
+    let request = factory().open_with_u32(DB_NAME, 1).unwrap();
+    settled(&request).await.unwrap();
+    let old: IdbDatabase = request.result().unwrap().unchecked_into();
+
+    assert!(IdbStore::open().await.is_err());
+    old.close();
+}
+
+#[wasm_bindgen_test]
+async fn closes_when_another_tab_upgrades() {
+    delete_database().await;
+    let (_store, stored) = IdbStore::open().await.unwrap();
+    assert!(stored.is_none());
+    // Blocks, and fails, unless the store closes its connection.
+    delete_database().await;
+}
@@ -0,0 +1,650 @@
+//! Unit tests of the todo model, run on the host with `cargo test`.
+
+use yew_wasm_pack_tw_template::due::{Due, Now};
+use yew_wasm_pack_tw_template::events::Action;
+use yew_wasm_pack_tw_template::model::{
+    Entry, Filter, Priority, Sort, SortOrder, State, DEFAULT_LIST,
+};
+use yew_wasm_pack_tw_template::schema::Persisted;
+
+fn entry(id: u64, description: &str, completed: bool) -> Entry {
+    Entry {
+        id,
+        description: description.into(),
+        completed,
+        ..Entry::default()
+    }
+}
+
+fn state() -> State {
+    State::new(Persisted {
+        next_id: 4,
+        entries: vec![
+            entry(1, "Buy milk", false),
+            entry(2, "Walk the dog", true),
+            entry(3, "Call mum", false),
+        ],
+        ..Persisted::default()
+    })
+}
+
+fn ids<'a>(entries: impl IntoIterator<Item = &'a Entry>) -> Vec<u64> {
+    entries.into_iter().map(|e| e.id).collect()
+}
+
+fn due(s: &str) -> Option<Due> {
+    Some(s.parse().unwrap())
+}
+
+/// 2024-05-01, 12:00.
+fn now() -> Now {
+    Now {
+        date: "2024-05-01".parse().unwrap(),
+        time: "12:00".parse().unwrap(),
+        ..Now::default()
+    }
+}
+
+#[test]
+fn filters_fit_by_completion() {
+    let now = now();
+    let open = entry(1, "", false);
+    let done = entry(2, "", true);
+    assert!(Filter::All.fit(&open, &now) && Filter::All.fit(&done, &now));
+    assert!(Filter::Active.fit(&open, &now) && !Filter::Active.fit(&done, &now));
+    assert!(!Filter::Completed.fit(&open, &now) && Filter::Completed.fit(&done, &now));
+}
+
+#[test]
+fn filters_fit_open_entries_by_due_date() {
+    let now = now();
+    let fits = |due_at: &str, completed: bool| -> Vec<Filter> {
+        let entry = Entry {
+            due: due(due_at),
+            ..entry(1, "", completed)
+        };
+        [Filter::Today, Filter::Upcoming, Filter::Overdue]
+            .into_iter()
+            .filter(|f| f.fit(&entry, &now))
+            .collect()
+    };
+    assert_eq!(fits("2024-04-30T23:59", false), [Filter::Overdue]);
+    assert_eq!(fits("2024-05-01T11:59", false), [Filter::Overdue]);
+    assert_eq!(fits("2024-05-01T12:00", false), [Filter::Today]);
+    assert_eq!(fits("2024-05-01", false), [Filter::Today]);
+    assert_eq!(fits("2024-05-02", false), [Filter::Upcoming]);
+    assert!(fits("2024-04-30", true).is_empty());
+    assert!(!Filter::Today.fit(&entry(1, "", false), &now));
+}
+
+#[test]
+fn sorts_by_due_date_with_undated_entries_last() {
+    let mut state = state();
+    for (id, due_at) in [(1, "2024-05-03"), (3, "2024-05-01T09:00")] {
+        state.apply(
+            0.0,
+            &Action::SetDue {
+                id,
+                due: due(due_at),
+            },
+        );
+    }
+    assert_eq!(ids(state.visible()), [1, 2, 3]);
+    state.set_sort(Sort::Due.into());
+    assert_eq!(ids(state.visible()), [3, 1, 2]);
+    state.set_sort(SortOrder {
+        by: Sort::Due,
+        descending: true,
+    });
+    assert_eq!(ids(state.visible()), [1, 3, 2]);
+}
+
+#[test]
+fn sorts_either_way() {
+    let mut state = state();
+    for (by, ascending, descending) in [
+        (Sort::Manual, [1, 2, 3], [3, 2, 1]),
+        (Sort::Created, [1, 2, 3], [3, 2, 1]),
+        (Sort::Alphabetical, [1, 3, 2], [2, 3, 1]),
+        (Sort::Completion, [1, 3, 2], [2, 1, 3]),
+    ] {
+        state.set_sort(by.into());
+        assert_eq!(ids(state.visible()), ascending, "{:?}", by);
+        state.set_sort(SortOrder {
+            by,
+            descending: true,
+        });
+        assert_eq!(ids(state.visible()), descending, "{:?} descending", by);
+    }
+}
+
+#[test]
+fn records_when_entries_change() {
+    let mut state = state();
+    let add = Action::Add {
+        id: 4,
+        description: "Water plants".into(),
+        list: DEFAULT_LIST,
+    };
+    state.apply(5.0, &add);
+    let tag = Action::AddTag {
+        id: 1,
+        tag: "home".into(),
+    };
+    state.apply(6.0, &tag);
+    // Neither a change that changes nothing nor moving counts.
+    state.apply(7.0, &tag);
+    state.apply(
+        8.0,
+        &Action::Reorder {
+            id: 4,
+            target: 1,
+            after: false,
+        },
+    );
+    let updated: Vec<_> = state.entries().iter().map(|e| e.updated_at).collect();
+    assert_eq!(updated, [Some(5.0), Some(6.0), None, None]);
+    assert_eq!(state.entry(4).unwrap().created_at, Some(5.0));
+    assert_eq!(state.entry(1).unwrap().created_at, None);
+
+    state.set_sort(SortOrder {
+        by: Sort::Modified,
+        descending: true,
+    });
+    assert_eq!(ids(state.visible()), [1, 4, 2, 3]);
+    assert!(state.entries().iter().all(|e| e.edited_at.is_none()));
+
+    // Only editing the description counts as an edit, and completing the
+    // entry afterwards keeps it.
+    let edit = Action::Edit {
+        id: 4,
+        description: "Water the plants".into(),
+    };
+    state.apply(9.0, &edit);
+    state.apply(10.0, &edit);
+    state.apply(11.0, &Action::Toggle { id: 4 });
+    let plants = state.entry(4).unwrap();
+    assert_eq!(plants.edited_at, Some(9.0));
+    assert_eq!(plants.updated_at, Some(11.0));
+}
+
+#[test]
+fn keeps_a_sort_per_list() {
+    let mut state = state();
+    with_work_list(&mut state);
+    state.set_sort(Sort::Alphabetical.into());
+    state.list = 10;
+    assert_eq!(state.sort(), SortOrder::default());
+    state.list = DEFAULT_LIST;
+    assert_eq!(state.sort().by, Sort::Alphabetical);
+
+    state.apply(0.0, &Action::RemoveList { id: DEFAULT_LIST });
+    assert!(state.sorts.is_empty());
+}
+
+#[test]
+fn visible_follows_the_filter() {
+    let mut state = state();
+    state.filter = Filter::Active;
+    assert_eq!(ids(state.visible()), [1, 3]);
+    state.filter = Filter::Completed;
+    assert_eq!(ids(state.visible()), [2]);
+}
+
+#[test]
+fn priority_filter_narrows_the_filter() {
+    let mut state = state();
+    for (id, priority) in [(1, Priority::High), (2, Priority::High), (3, Priority::Low)] {
+        state.apply(0.0, &Action::SetPriority { id, priority });
+    }
+    state.priority_filter = Some(Priority::High);
+    assert_eq!(ids(state.visible()), [1, 2]);
+    state.filter = Filter::Active;
+    assert_eq!(ids(state.visible()), [1]);
+    state.priority_filter = Some(Priority::None);
+    assert!(state.visible().is_empty());
+    assert!(!state.is_all_completed());
+}
+
+#[test]
+fn sorts_by_priority_keeping_list_order_within_one() {
+    let mut state = state();
+    for (id, priority) in [(2, Priority::Medium), (3, Priority::High)] {
+        state.apply(0.0, &Action::SetPriority { id, priority });
+    }
+    state.set_sort(Sort::Priority.into());
+    assert_eq!(ids(state.visible()), [3, 2, 1]);
+    state.apply(
+        0.0,
+        &Action::SetPriority {
+            id: 2,
+            priority: Priority::High,
+        },
+    );
+    assert_eq!(ids(state.visible()), [2, 3, 1]);
+}
+
+#[test]
+fn tags_are_added_once_and_listed_sorted() {
+    let mut state = state();
+    for (id, tag) in [(1, "home"), (1, "errands"), (1, "home"), (3, "calls")] {
+        state.apply(
+            0.0,
+            &Action::AddTag {
+                id,
+                tag: tag.into(),
+            },
+        );
+    }
+    assert_eq!(state.entry(1).unwrap().tags, ["home", "errands"]);
+    assert_eq!(state.tags(), ["calls", "errands", "home"]);
+    state.apply(
+        0.0,
+        &Action::RemoveTag {
+            id: 1,
+            tag: "home".into(),
+        },
+    );
+    assert_eq!(state.tags(), ["calls", "errands"]);
+}
+
+#[test]
+fn tag_filter_combines_with_the_filter() {
+    let mut state = state();
+    for id in [1, 2] {
+        state.apply(
+            0.0,
+            &Action::AddTag {
+                id,
+                tag: "errands".into(),
+            },
+        );
+    }
+    state.tag_filter = Some("errands".into());
+    assert_eq!(ids(state.visible()), [1, 2]);
+    state.filter = Filter::Completed;
+    assert_eq!(ids(state.visible()), [2]);
+}
+
+/// Adds subtasks 10, 11 and 12 to entry 1.
+fn with_subtasks(state: &mut State) {
+    for (subtask, description) in [(10, "Oat"), (11, "Soy"), (12, "Rice")] {
+        state.apply(
+            0.0,
+            &Action::AddSubtask {
+                id: 1,
+                subtask,
+                description: description.into(),
+            },
+        );
+    }
+}
+
+#[test]
+fn subtasks_report_progress() {
+    let mut state = state();
+    assert_eq!(state.entry(1).unwrap().progress(), None);
+    with_subtasks(&mut state);
+    state.apply(0.0, &Action::ToggleSubtask { id: 1, subtask: 11 });
+    assert_eq!(state.entry(1).unwrap().progress(), Some((1, 3)));
+    assert!(state.next_id() > 12);
+}
+
+#[test]
+fn completing_an_entry_completes_its_subtasks() {
+    let mut state = state();
+    with_subtasks(&mut state);
+    state.apply(0.0, &Action::Toggle { id: 1 });
+    assert_eq!(state.entry(1).unwrap().progress(), Some((3, 3)));
+    // Reopening leaves them done.
+    state.apply(0.0, &Action::Toggle { id: 1 });
+    assert!(!state.entry(1).unwrap().completed);
+    assert_eq!(state.entry(1).unwrap().progress(), Some((3, 3)));
+
+    state.apply(0.0, &Action::ToggleSubtask { id: 1, subtask: 10 });
+    state.apply(
+        0.0,
+        &Action::SetCompleted {
+            ids: vec![1],
+            completed: true,
+        },
+    );
+    assert_eq!(state.entry(1).unwrap().progress(), Some((3, 3)));
+}
+
+#[test]
+fn auto_complete_follows_the_subtasks() {
+    let mut state = state();
+    with_subtasks(&mut state);
+    state.apply(
+        0.0,
+        &Action::SetAutoComplete {
+            id: 1,
+            auto_complete: true,
+        },
+    );
+    assert!(!state.entry(1).unwrap().completed);
+    for subtask in [10, 11, 12] {
+        state.apply(0.0, &Action::ToggleSubtask { id: 1, subtask });
+    }
+    assert!(state.entry(1).unwrap().completed);
+    state.apply(0.0, &Action::ToggleSubtask { id: 1, subtask: 11 });
+    assert!(!state.entry(1).unwrap().completed);
+    state.apply(0.0, &Action::RemoveSubtask { id: 1, subtask: 11 });
+    assert!(state.entry(1).unwrap().completed);
+}
+
+#[test]
+fn reopening_an_entry_stops_it_following_done_subtasks() {
+    let mut state = state();
+    with_subtasks(&mut state);
+    state.apply(
+        0.0,
+        &Action::SetAutoComplete {
+            id: 1,
+            auto_complete: true,
+        },
+    );
+    state.apply(0.0, &Action::Toggle { id: 1 });
+    assert!(state.entry(1).unwrap().completed);
+    state.apply(0.0, &Action::Toggle { id: 1 });
+    let entry = state.entry(1).unwrap();
+    assert!(!entry.completed);
+    assert!(!entry.auto_complete);
+    assert_eq!(entry.progress(), Some((3, 3)));
+}
+
+#[test]
+fn filters_go_by_the_entry_not_its_subtasks() {
+    let mut state = state();
+    with_subtasks(&mut state);
+    state.apply(0.0, &Action::ToggleSubtask { id: 1, subtask: 10 });
+    state.filter = Filter::Completed;
+    assert_eq!(ids(state.visible()), [2]);
+}
+
+#[test]
+fn clear_completed_keeps_the_subtasks_of_open_entries() {
+    let mut state = state();
+    with_subtasks(&mut state);
+    state.apply(0.0, &Action::ToggleSubtask { id: 1, subtask: 11 });
+    state.apply(0.0, &Action::ClearCompleted { list: None });
+    assert_eq!(ids(state.entries()), [1, 3]);
+    assert_eq!(state.entry(1).unwrap().progress(), Some((1, 3)));
+}
+
+#[test]
+fn counts_entries() {
+    let state = state();
+    assert_eq!(state.total(), 3);
+    assert_eq!(state.total_completed(), 1);
+}
+
+#[test]
+fn toggle_all_only_touches_the_filtered_entries() {
+    let mut state = state();
+    state.apply(
+        0.0,
+        &Action::ToggleAll {
+            filter: Filter::Completed,
+            completed: false,
+        },
+    );
+    assert_eq!(state.total_completed(), 0);
+
+    state.filter = Filter::Active;
+    assert!(!state.is_all_completed());
+    state.apply(
+        0.0,
+        &Action::ToggleAll {
+            filter: Filter::Active,
+            completed: true,
+        },
+    );
+    assert_eq!(state.total_completed(), 3);
+}
+
+#[test]
+fn set_completed_only_touches_the_given_entries() {
+    let mut state = state();
+    state.apply(
+        0.0,
+        &Action::SetCompleted {
+            ids: vec![1, 2, 42],
+            completed: true,
+        },
+    );
+    assert_eq!(state.total_completed(), 2);
+    assert!(!state.entry(3).unwrap().completed);
+}
+
+#[test]
+fn is_all_completed_is_false_without_visible_entries() {
+    let mut state = state();
+    state.filter = Filter::Completed;
+    state.apply(0.0, &Action::Toggle { id: 2 });
+    assert!(!state.is_all_completed());
+}
+
+#[test]
+fn clear_completed_keeps_active_entries() {
+    let mut state = state();
+    state.apply(0.0, &Action::ClearCompleted { list: None });
+    assert_eq!(ids(state.entries()), [1, 3]);
+}
+
+#[test]
+fn removes_by_id_regardless_of_filter() {
+    let mut state = state();
+    state.filter = Filter::Active;
+    state.apply(0.0, &Action::Remove { id: 3 });
+    assert_eq!(ids(state.entries()), [1, 2]);
+    state.apply(0.0, &Action::Remove { id: 42 });
+    assert_eq!(ids(state.entries()), [1, 2]);
+}
+
+#[test]
+fn adding_keeps_ids_unique() {
+    let mut state = state();
+    let id = state.allocate_id();
+    state.apply(
+        0.0,
+        &Action::Add {
+            id,
+            description: "Water plants".into(),
+            list: DEFAULT_LIST,
+        },
+    );
+    assert_eq!(id, 4);
+    assert_eq!(state.next_id(), 5);
+    state.apply(
+        0.0,
+        &Action::Replace {
+            entries: vec![entry(9, "Imported", false)],
+            lists: Vec::new(),
+        },
+    );
+    assert_eq!(state.next_id(), 10);
+}
+
+#[test]
+fn editing_ends_when_the_entry_goes_away() {
+    let mut state = state();
+    state.toggle_edit(1);
+    assert_eq!(state.editing, Some(1));
+    state.apply(0.0, &Action::Remove { id: 1 });
+    assert_eq!(state.editing, None);
+    state.toggle_edit(42);
+    assert_eq!(state.editing, None);
+}
+
+/// Adds a "Work" list with id 10 holding entry 3.
+fn with_work_list(state: &mut State) {
+    state.apply(
+        0.0,
+        &Action::AddList {
+            id: 10,
+            name: "Work".into(),
+        },
+    );
+    state.apply(0.0, &Action::MoveEntry { id: 3, list: 10 });
+}
+
+#[test]
+fn shows_and_counts_the_current_list_only() {
+    let mut state = state();
+    with_work_list(&mut state);
+    assert_eq!(ids(state.visible()), [1, 2]);
+    assert_eq!(state.total(), 2);
+    state.list = 10;
+    assert_eq!(ids(state.visible()), [3]);
+    assert_eq!((state.total(), state.total_completed()), (1, 0));
+    assert_eq!(state.list_named("work").map(|l| l.id), Some(10));
+}
+
+#[test]
+fn clear_completed_only_clears_the_given_list() {
+    let mut state = state();
+    with_work_list(&mut state);
+    state.apply(0.0, &Action::Toggle { id: 3 });
+    state.apply(0.0, &Action::ClearCompleted { list: Some(10) });
+    assert_eq!(ids(state.entries()), [1, 2]);
+}
+
+#[test]
+fn removing_a_list_removes_its_entries() {
+    let mut state = state();
+    with_work_list(&mut state);
+    state.list = 10;
+    state.apply(0.0, &Action::RemoveList { id: 10 });
+    assert_eq!(ids(state.entries()), [1, 2]);
+    assert_eq!(state.list, DEFAULT_LIST);
+
+    // The last list stays.
+    state.apply(0.0, &Action::RemoveList { id: DEFAULT_LIST });
+    assert_eq!(state.lists().len(), 1);
+    assert_eq!(ids(state.entries()), [1, 2]);
+}
+
+#[test]
+fn entries_only_go_to_lists_that_exist() {
+    let mut state = state();
+    state.apply(0.0, &Action::MoveEntry { id: 1, list: 42 });
+    assert_eq!(state.entry(1).unwrap().list, DEFAULT_LIST);
+    state.apply(
+        0.0,
+        &Action::Add {
+            id: 4,
+            description: "Stray".into(),
+            list: 42,
+        },
+    );
+    assert_eq!(state.entry(4).unwrap().list, DEFAULT_LIST);
+}
+
+#[test]
+fn reorders_next_to_the_target() {
+    let mut state = state();
+    state.apply(
+        0.0,
+        &Action::Reorder {
+            id: 3,
+            target: 1,
+            after: false,
+        },
+    );
+    assert_eq!(ids(state.entries()), [3, 1, 2]);
+    state.apply(
+        0.0,
+        &Action::Reorder {
+            id: 3,
+            target: 2,
+            after: true,
+        },
+    );
+    assert_eq!(ids(state.entries()), [1, 2, 3]);
+    // Unknown entries and moving onto itself change nothing.
+    for (id, target) in [(42, 1), (1, 42), (1, 1)] {
+        state.apply(
+            0.0,
+            &Action::Reorder {
+                id,
+                target,
+                after: true,
+            },
+        );
+        assert_eq!(ids(state.entries()), [1, 2, 3]);
+    }
+}
+
+#[test]
+fn neighbours_skip_hidden_entries() {
+    let mut state = state();
+    state.filter = Filter::Active;
+    let reorder = |id, target, after| Some(Action::Reorder { id, target, after });
+    assert_eq!(state.move_past(1, true), reorder(1, 3, true));
+    assert_eq!(state.move_past(1, false), None);
+    assert_eq!(state.move_past(2, true), None);
+    assert_eq!(state.drop_onto(1, 3), reorder(1, 3, true));
+    assert_eq!(state.drop_onto(3, 3), None);
+
+    // Moving 3 up past 1 keeps the hidden entry 2 after both.
+    let action = state.move_past(3, false).unwrap();
+    assert_eq!(action, reorder(3, 1, false).unwrap());
+    state.apply(0.0, &action);
+    assert_eq!(ids(state.entries()), [3, 1, 2]);
+}
+
+#[test]
+fn moves_the_other_way_when_shown_in_reverse() {
+    let mut state = state();
+    state.set_sort(SortOrder {
+        by: Sort::Manual,
+        descending: true,
+    });
+    // Shown as 3, 2, 1, so moving 3 down puts it before 2 in the list.
+    let action = state.move_past(3, true).unwrap();
+    state.apply(0.0, &action);
+    assert_eq!(ids(state.entries()), [1, 3, 2]);
+    assert_eq!(ids(state.visible()), [2, 3, 1]);
+
+    state.set_sort(Sort::Alphabetical.into());
+    assert_eq!(state.move_past(3, true), None);
+    assert_eq!(state.drop_onto(3, 1), None);
+}
+
+#[test]
+fn records_when_entries_are_completed() {
+    let mut state = state();
+    state.apply(3.0, &Action::Toggle { id: 1 });
+    let milk = state.entry(1).unwrap();
+    assert_eq!((milk.completed_at, milk.updated_at), (Some(3.0), Some(3.0)));
+    // Completing it again keeps the first time.
+    state.apply(
+        4.0,
+        &Action::SetCompleted {
+            ids: vec![1],
+            completed: true,
+        },
+    );
+    assert_eq!(state.entry(1).unwrap().completed_at, Some(3.0));
+    state.apply(5.0, &Action::Toggle { id: 1 });
+    assert_eq!(state.entry(1).unwrap().completed_at, None);
+
+    // So does finishing the last subtask of one that completes with them.
+    with_subtasks(&mut state);
+    state.apply(
+        6.0,
+        &Action::SetAutoComplete {
+            id: 1,
+            auto_complete: true,
+        },
+    );
+    for subtask in [10, 11, 12] {
+        state.apply(
+            7.0 + subtask as f64,
+            &Action::ToggleSubtask { id: 1, subtask },
+        );
+    }
+    assert_eq!(state.entry(1).unwrap().completed_at, Some(19.0));
+}
@@ -0,0 +1,452 @@
+//! Property tests that run random sequences of operations against `State`
+//! and check that its invariants hold after every step.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use proptest::prelude::*;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use strum::IntoEnumIterator;
+use yew_wasm_pack_tw_template::due::{Date, Due, Now, Time};
+use yew_wasm_pack_tw_template::events::{Action, EventLog};
+use yew_wasm_pack_tw_template::history::{History, Snapshot};
+use yew_wasm_pack_tw_template::model::{
+    Entry, EntryId, Filter, ListId, Priority, Sort, SortOrder, State,
+};
+use yew_wasm_pack_tw_template::schema::{self, Persisted};
+use yew_wasm_pack_tw_template::validation;
+
+/// What a user can do through the UI. Targets are picked by index into the
+/// current entries, and an index past the end picks an id that doesn't
+/// exist, like a stale message for an entry removed in the meantime.
+#[derive(Clone, Debug)]
+enum Op {
+    Add(String),
+    Edit(usize, String),
+    Remove(usize),
+    Toggle(usize),
+    ToggleAll(Filter),
+    ClearCompleted,
+    SetFilter(Filter),
+    ToggleEdit(usize),
+    SetDue(usize, Option<Due>),
+    SetSort(SortOrder),
+    SetPriority(usize, Priority),
+    SetPriorityFilter(Option<Priority>),
+    AddTag(usize, String),
+    RemoveTag(usize, String),
+    SetTagFilter(Option<String>),
+    SetContext(usize, Option<String>),
+    AddSubtask(usize, String),
+    /// Toggles a subtask picked by index into the target's subtasks.
+    ToggleSubtask(usize, usize),
+    RemoveSubtask(usize, usize),
+    SetAutoComplete(usize, bool),
+    AddList(String),
+    /// Lists are picked by index like entries are.
+    RemoveList(usize),
+    MoveEntry(usize, usize),
+    SelectList(usize),
+    /// Alt+Up or Alt+Down on an entry.
+    Reorder(usize, bool),
+    /// Drags one entry onto another.
+    Drop(usize, usize),
+}
+
+/// The time every generated state is looked at, noon on 2024-05-01.
+const NOW: Now = Now {
+    date: Date {
+        year: 2024,
+        month: 5,
+        day: 1,
+    },
+    time: Time {
+        hour: 12,
+        minute: 0,
+    },
+    timestamp: 0.0,
+};
+
+fn filter() -> impl Strategy<Value = Filter> {
+    prop::sample::select(Filter::iter().collect::<Vec<_>>())
+}
+
+/// Due dates from two days before [`NOW`] to two days after.
+fn due() -> impl Strategy<Value = Option<Due>> {
+    let time = (0..24u32, 0..60u32).prop_map(|(hour, minute)| Time { hour, minute });
+    prop::option::of((29..34u32, prop::option::of(time)).prop_map(|(day, time)| {
+        let (month, day) = if day > 30 { (5, day - 30) } else { (4, day) };
+        Due {
+            date: Date {
+                year: 2024,
+                month,
+                day,
+            },
+            time,
+        }
+    }))
+}
+
+fn priority() -> impl Strategy<Value = Priority> {
+    prop::sample::select(Priority::iter().collect::<Vec<_>>())
+}
+
+/// A few tags, so that entries share them.
+fn tag() -> impl Strategy<Value = String> {
+    prop::sample::select(vec!["home".to_string(), "work".into(), "errands".into()])
+}
+
+fn new_state() -> State {
+    let mut state = State::new(Persisted::default());
+    state.now = NOW;
+    state
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    let target = 0..8usize;
+    prop_oneof![
+        3 => ".{0,12}".prop_map(Op::Add),
+        1 => (target.clone(), ".{0,12}").prop_map(|(idx, s)| Op::Edit(idx, s)),
+        1 => target.clone().prop_map(Op::Remove),
+        2 => target.clone().prop_map(Op::Toggle),
+        1 => filter().prop_map(Op::ToggleAll),
+        1 => Just(Op::ClearCompleted),
+        1 => filter().prop_map(Op::SetFilter),
+        1 => target.clone().prop_map(Op::ToggleEdit),
+        2 => (target.clone(), due()).prop_map(|(idx, due)| Op::SetDue(idx, due)),
+        2 => (target.clone(), priority()).prop_map(|(idx, p)| Op::SetPriority(idx, p)),
+        1 => prop::option::of(priority()).prop_map(Op::SetPriorityFilter),
+        2 => (target.clone(), tag()).prop_map(|(idx, tag)| Op::AddTag(idx, tag)),
+        1 => (target.clone(), tag()).prop_map(|(idx, tag)| Op::RemoveTag(idx, tag)),
+        1 => prop::option::of(tag()).prop_map(Op::SetTagFilter),
+        1 => (target.clone(), prop::option::of(tag()))
+            .prop_map(|(idx, context)| Op::SetContext(idx, context)),
+        2 => (target.clone(), ".{0,12}").prop_map(|(idx, s)| Op::AddSubtask(idx, s)),
+        2 => (target.clone(), 0..4usize).prop_map(|(idx, sub)| Op::ToggleSubtask(idx, sub)),
+        1 => (target.clone(), 0..4usize).prop_map(|(idx, sub)| Op::RemoveSubtask(idx, sub)),
+        1 => (target.clone(), any::<bool>()).prop_map(|(idx, a)| Op::SetAutoComplete(idx, a)),
+        1 => tag().prop_map(Op::AddList),
+        1 => (0..4usize).prop_map(Op::RemoveList),
+        2 => (target.clone(), 0..4usize).prop_map(|(idx, list)| Op::MoveEntry(idx, list)),
+        1 => (0..4usize).prop_map(Op::SelectList),
+        2 => (target.clone(), any::<bool>()).prop_map(|(idx, after)| Op::Reorder(idx, after)),
+        1 => (target.clone(), target.clone()).prop_map(|(idx, onto)| Op::Drop(idx, onto)),
+        2 => (prop::sample::select(Sort::iter().collect::<Vec<_>>()), any::<bool>())
+            .prop_map(|(by, descending)| Op::SetSort(SortOrder { by, descending })),
+    ]
+}
+
+fn target(state: &State, idx: usize) -> EntryId {
+    match state.entries().get(idx) {
+        Some(entry) => entry.id,
+        None => state.next_id() + idx as EntryId,
+    }
+}
+
+fn list(state: &State, idx: usize) -> ListId {
+    match state.lists().get(idx) {
+        Some(list) => list.id,
+        None => state.next_id() + idx as ListId,
+    }
+}
+
+fn subtask(state: &State, idx: usize, sub: usize) -> (EntryId, EntryId) {
+    let id = target(state, idx);
+    let subtask = match state.entry(id).and_then(|e| e.subtasks.get(sub)) {
+        Some(subtask) => subtask.id,
+        None => state.next_id() + sub as EntryId,
+    };
+    (id, subtask)
+}
+
+/// Runs an operation the way `App::update` does at time `at`, returning the
+/// action it dispatched, if any.
+fn run(state: &mut State, at: f64, op: Op) -> Option<Action> {
+    let action = match op {
+        Op::Add(description) => Action::Add {
+            id: state.allocate_id(),
+            description,
+            list: state.list,
+        },
+        Op::Edit(idx, description) => Action::Edit {
+            id: target(state, idx),
+            description,
+        },
+        Op::Remove(idx) => Action::Remove {
+            id: target(state, idx),
+        },
+        Op::Toggle(idx) => Action::Toggle {
+            id: target(state, idx),
+        },
+        Op::ToggleAll(filter) => {
+            state.filter = filter;
+            state.toggle_visible()
+        }
+        Op::ClearCompleted => Action::ClearCompleted {
+            list: Some(state.list),
+        },
+        Op::SetFilter(filter) => {
+            state.filter = filter;
+            return None;
+        }
+        Op::ToggleEdit(idx) => {
+            state.toggle_edit(target(state, idx));
+            return None;
+        }
+        Op::SetDue(idx, due) => Action::SetDue {
+            id: target(state, idx),
+            due,
+        },
+        Op::SetSort(sort) => {
+            state.set_sort(sort);
+            return None;
+        }
+        Op::SetPriority(idx, priority) => Action::SetPriority {
+            id: target(state, idx),
+            priority,
+        },
+        Op::SetPriorityFilter(priority) => {
+            state.priority_filter = priority;
+            return None;
+        }
+        Op::AddTag(idx, tag) => Action::AddTag {
+            id: target(state, idx),
+            tag,
+        },
+        Op::RemoveTag(idx, tag) => Action::RemoveTag {
+            id: target(state, idx),
+            tag,
+        },
+        Op::SetTagFilter(tag) => {
+            state.tag_filter = tag;
+            return None;
+        }
+        Op::SetContext(idx, context) => Action::SetContext {
+            id: target(state, idx),
+            context,
+        },
+        Op::AddSubtask(idx, description) => Action::AddSubtask {
+            id: target(state, idx),
+            subtask: state.allocate_id(),
+            description,
+        },
+        Op::ToggleSubtask(idx, sub) => {
+            let (id, subtask) = subtask(state, idx, sub);
+            Action::ToggleSubtask { id, subtask }
+        }
+        Op::RemoveSubtask(idx, sub) => {
+            let (id, subtask) = subtask(state, idx, sub);
+            Action::RemoveSubtask { id, subtask }
+        }
+        Op::SetAutoComplete(idx, auto_complete) => Action::SetAutoComplete {
+            id: target(state, idx),
+            auto_complete,
+        },
+        Op::AddList(name) => {
+            let name = validation::list_name(state.lists(), &name, None)?;
+            Action::AddList {
+                id: state.allocate_id(),
+                name,
+            }
+        }
+        Op::RemoveList(idx) => Action::RemoveList {
+            id: list(state, idx),
+        },
+        Op::MoveEntry(idx, to) => Action::MoveEntry {
+            id: target(state, idx),
+            list: list(state, to),
+        },
+        Op::Reorder(idx, below) => state.move_past(target(state, idx), below)?,
+        Op::Drop(idx, onto) => state.drop_onto(target(state, idx), target(state, onto))?,
+        Op::SelectList(idx) => {
+            // The sidebar only links to lists that exist.
+            if let Some(list) = state.lists().get(idx) {
+                state.list = list.id;
+            }
+            return None;
+        }
+    };
+    state.apply(at, &action);
+    Some(action)
+}
+
+fn check_invariants(state: &State) -> Result<(), TestCaseError> {
+    prop_assert!(state.total_completed() <= state.total());
+
+    // Subtasks share the id sequence of entries.
+    let ids: Vec<EntryId> = state
+        .entries()
+        .iter()
+        .flat_map(|e| std::iter::once(e.id).chain(e.subtasks.iter().map(|s| s.id)))
+        .collect();
+    let unique: HashSet<EntryId> = ids.iter().copied().collect();
+    prop_assert_eq!(unique.len(), ids.len(), "duplicate ids in {:?}", ids);
+    prop_assert!(ids.iter().all(|&id| id < state.next_id()));
+
+    prop_assert!(!state.lists().is_empty());
+    prop_assert!(state.lists().iter().any(|l| l.id == state.list));
+    for entry in state.entries() {
+        prop_assert!(state.lists().iter().any(|l| l.id == entry.list));
+    }
+
+    // Counts and filters only look at the list shown.
+    let now = &state.now;
+    let count = |filter: Filter| {
+        state
+            .in_list(state.list)
+            .filter(|e| filter.fit(e, now))
+            .count()
+    };
+    prop_assert_eq!(
+        count(Filter::Active) + count(Filter::Completed),
+        state.total()
+    );
+    prop_assert_eq!(count(Filter::Completed), state.total_completed());
+    // Every open entry with a due date is under exactly one due filter.
+    let open_due = state
+        .in_list(state.list)
+        .filter(|e| !e.completed && e.due.is_some())
+        .count();
+    prop_assert_eq!(
+        count(Filter::Today) + count(Filter::Upcoming) + count(Filter::Overdue),
+        open_due
+    );
+
+    let visible = state.visible();
+    let shown = |e: &Entry| {
+        e.list == state.list
+            && state.filter.fit(e, now)
+            && state.priority_filter.is_none_or(|p| e.priority == p)
+            && state.tag_filter.as_ref().is_none_or(|t| e.tags.contains(t))
+    };
+    for entry in state.entries() {
+        let unique: HashSet<&String> = entry.tags.iter().collect();
+        prop_assert_eq!(unique.len(), entry.tags.len(), "duplicate tags");
+        if let Some((done, total)) = entry.progress().filter(|_| entry.auto_complete) {
+            prop_assert_eq!(entry.completed, done == total, "auto-complete out of step");
+        }
+    }
+    prop_assert_eq!(
+        visible.len(),
+        state.entries().iter().filter(|e| shown(e)).count()
+    );
+    prop_assert!(visible.iter().all(|e| shown(e)));
+    let order = state.sort();
+    let directed = |ordering: Ordering| {
+        if order.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    };
+    // Whether two neighbours are in order, with missing values last.
+    let in_order = |a: &Entry, b: &Entry| match order.by {
+        Sort::Manual => true,
+        Sort::Created => directed(a.id.cmp(&b.id)) == Ordering::Less,
+        Sort::Modified => match (a.updated_at, b.updated_at) {
+            (Some(a), Some(b)) => directed(a.total_cmp(&b)) != Ordering::Greater,
+            (a, b) => a.is_some() || b.is_none(),
+        },
+        Sort::Alphabetical => {
+            let (a, b) = (a.description.to_lowercase(), b.description.to_lowercase());
+            directed(a.cmp(&b)) != Ordering::Greater
+        }
+        Sort::Completion => directed(a.completed.cmp(&b.completed)) != Ordering::Greater,
+        Sort::Due => match (a.due, b.due) {
+            (Some(a), Some(b)) => directed(a.cmp(&b)) != Ordering::Greater,
+            (a, b) => a.is_some() || b.is_none(),
+        },
+        Sort::Priority => directed(b.priority.cmp(&a.priority)) != Ordering::Greater,
+    };
+    prop_assert!(visible.windows(2).all(|pair| in_order(pair[0], pair[1])));
+    if order.by == Sort::Manual {
+        let mut listed: Vec<EntryId> = state
+            .entries()
+            .iter()
+            .filter(|e| shown(e))
+            .map(|e| e.id)
+            .collect();
+        if order.descending {
+            listed.reverse();
+        }
+        let ids: Vec<EntryId> = visible.iter().map(|e| e.id).collect();
+        prop_assert_eq!(ids, listed);
+    }
+
+    if let Some(id) = state.editing {
+        prop_assert!(state.entry(id).is_some());
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn invariants_hold_after_every_operation(ops in prop::collection::vec(op(), 0..64)) {
+        let mut state = new_state();
+        for (at, op) in ops.into_iter().enumerate() {
+            run(&mut state, at as f64, op);
+            check_invariants(&state)?;
+            // Every entry was added by an operation, so it knows when it
+            // was added, changed and completed.
+            for entry in state.entries() {
+                let (created, updated) = (entry.created_at.unwrap(), entry.updated_at.unwrap());
+                prop_assert!(created <= updated && updated <= at as f64);
+                prop_assert_eq!(entry.completed_at.is_some(), entry.completed);
+                prop_assert!(entry.completed_at.is_none_or(|completed| completed <= updated));
+                prop_assert!(entry.edited_at.is_none_or(|edited| created < edited && edited <= updated));
+            }
+        }
+    }
+
+    #[test]
+    fn persisted_entries_round_trip(ops in prop::collection::vec(op(), 0..64)) {
+        let mut state = new_state();
+        for (at, op) in ops.into_iter().enumerate() {
+            run(&mut state, at as f64, op);
+        }
+        let persisted = state.to_persisted();
+        let raw = schema::encode(persisted.next_id, &persisted.lists, &persisted.entries);
+        prop_assert_eq!(schema::decode(&raw).unwrap(), persisted.clone());
+        let reloaded = State::new(persisted);
+        prop_assert_eq!(reloaded.entries(), state.entries());
+        prop_assert_eq!(reloaded.lists(), state.lists());
+        prop_assert_eq!(reloaded.next_id(), state.next_id());
+    }
+
+    #[test]
+    fn replaying_the_log_reproduces_the_entries(ops in prop::collection::vec(op(), 0..64)) {
+        let mut state = new_state();
+        let mut log = EventLog::new(state.to_persisted());
+        for (at, op) in ops.into_iter().enumerate() {
+            if let Some(action) = run(&mut state, at as f64, op) {
+                log.record(at as f64, action);
+            }
+        }
+        let log = EventLog::decode(&log.encode()).unwrap();
+        let replayed = log.replay();
+        prop_assert_eq!(replayed.entries(), state.entries());
+        prop_assert_eq!(replayed.lists(), state.lists());
+    }
+
+    #[test]
+    fn undo_walks_back_through_every_change(ops in prop::collection::vec(op(), 0..32)) {
+        let mut state = new_state();
+        let mut history = History::default();
+        let mut snapshots = vec![Snapshot::of(&state)];
+        for (at, op) in ops.into_iter().enumerate() {
+            run(&mut state, at as f64, op);
+            let after = Snapshot::of(&state);
+            if after != *snapshots.last().unwrap() {
+                history.record(snapshots.last().unwrap(), &after, None);
+                snapshots.push(after);
+            }
+        }
+        let mut history = History::decode(&history.encode()).unwrap();
+        let mut current = snapshots.pop().unwrap();
+        while let Some(previous) = history.undo(&current) {
+            prop_assert_eq!(Some(&previous), snapshots.last());
+            current = snapshots.pop().unwrap();
+        }
+        prop_assert!(snapshots.is_empty());
+    }
+}
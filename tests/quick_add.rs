@@ -0,0 +1,127 @@
+use yew_wasm_pack_tw_template::due::{Due, Now};
+use yew_wasm_pack_tw_template::events::Action;
+use yew_wasm_pack_tw_template::model::{Priority, State, DEFAULT_LIST};
+use yew_wasm_pack_tw_template::quick_add::QuickAdd;
+use yew_wasm_pack_tw_template::schema::Persisted;
+
+/// A Wednesday.
+fn now() -> Now {
+    Now {
+        date: "2024-05-01".parse().unwrap(),
+        time: "12:00".parse().unwrap(),
+        ..Now::default()
+    }
+}
+
+fn parse(raw: &str) -> QuickAdd {
+    QuickAdd::parse(raw, &now())
+}
+
+fn due(s: &str) -> Option<Due> {
+    Some(s.parse().unwrap())
+}
+
+#[test]
+fn takes_fields_out_of_the_description() {
+    assert_eq!(
+        parse("Buy  milk #Errands !high @store due:2024-05-03 #home"),
+        QuickAdd {
+            description: "Buy milk".into(),
+            due: due("2024-05-03"),
+            priority: Some(Priority::High),
+            tags: vec!["errands".into(), "home".into()],
+            context: Some("store".into()),
+        }
+    );
+}
+
+#[test]
+fn leaves_plain_descriptions_alone() {
+    let parsed = parse("Read chapter 5 at the library");
+    assert_eq!(parsed.description, "Read chapter 5 at the library");
+    assert!(!parsed.has_fields());
+}
+
+#[test]
+fn keeps_what_it_does_not_recognize() {
+    for raw in [
+        "Fix #",
+        "Say hi !",
+        "Email bob@example.com",
+        "!none",
+        "due:soon",
+    ] {
+        let parsed = parse(raw);
+        assert_eq!(parsed.description, raw);
+        assert!(!parsed.has_fields(), "{:?} set {:?}", raw, parsed);
+    }
+}
+
+#[test]
+fn understands_relative_days_and_times() {
+    for (raw, expected) in [
+        ("Call mum tomorrow 5pm", "2024-05-02T17:00"),
+        ("Call mum at 5:30pm tomorrow", "2024-05-02T17:30"),
+        ("Call mum today", "2024-05-01"),
+        ("Call mum on friday", "2024-05-03"),
+        ("Call mum next wednesday", "2024-05-08"),
+        ("Call mum by 18:45", "2024-05-01T18:45"),
+        ("Call mum 12am", "2024-05-02T00:00"),
+        ("Call mum tomorrow at 17:30", "2024-05-02T17:30"),
+        ("Call mum friday #family", "2024-05-03"),
+        ("Call mum due:Tomorrow", "2024-05-02"),
+        ("Call mum due:2024-06-01T09:00", "2024-06-01T09:00"),
+    ] {
+        let parsed = parse(raw);
+        assert_eq!(parsed.description, "Call mum", "{:?}", raw);
+        assert_eq!(parsed.due, due(expected), "{:?}", raw);
+    }
+}
+
+#[test]
+fn keeps_days_and_times_that_are_part_of_the_description() {
+    for raw in [
+        "Read John 3:16",
+        "Watch Friday Night Lights",
+        "Plan today's talk",
+        "Tomorrow never dies",
+        "Buy 5pm tickets",
+    ] {
+        let parsed = parse(raw);
+        assert_eq!(parsed.description, raw);
+        assert_eq!(parsed.due, None, "{:?}", raw);
+    }
+    let parsed = parse("Watch Friday Night Lights on sunday");
+    assert_eq!(parsed.description, "Watch Friday Night Lights");
+    assert_eq!(parsed.due, due("2024-05-05"));
+}
+
+#[test]
+fn only_drops_prepositions_before_a_date() {
+    assert_eq!(parse("Meet at noon").description, "Meet at noon");
+    assert_eq!(parse("Pay rent by 13pm").description, "Pay rent by 13pm");
+}
+
+#[test]
+fn creates_the_entry_with_its_fields() {
+    let mut state = State::new(Persisted::default());
+    let parsed = parse("Buy milk !low #errands @store tomorrow");
+    for action in parsed.actions(1, DEFAULT_LIST) {
+        state.apply(0.0, &action);
+    }
+    let entry = state.entry(1).unwrap();
+    assert_eq!(entry.description, "Buy milk");
+    assert_eq!(entry.due, due("2024-05-02"));
+    assert_eq!(entry.priority, Priority::Low);
+    assert_eq!(entry.tags, ["errands"]);
+    assert_eq!(entry.context.as_deref(), Some("store"));
+
+    state.apply(
+        0.0,
+        &Action::SetContext {
+            id: 1,
+            context: None,
+        },
+    );
+    assert_eq!(state.entry(1).unwrap().context, None);
+}
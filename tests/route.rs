@@ -0,0 +1,181 @@
+use yew_wasm_pack_tw_template::events::Action;
+use yew_wasm_pack_tw_template::model::{Filter, Sort, SortOrder, State, DEFAULT_LIST};
+use yew_wasm_pack_tw_template::route::Route;
+use yew_wasm_pack_tw_template::schema::Persisted;
+
+fn route(filter: Filter, entry: Option<u64>) -> Route {
+    Route {
+        list: None,
+        filter,
+        sort: None,
+        tag: None,
+        entry,
+    }
+}
+
+fn tagged(filter: Filter, tag: &str, entry: Option<u64>) -> Route {
+    Route {
+        tag: Some(tag.into()),
+        ..route(filter, entry)
+    }
+}
+
+fn in_list(list: &str, filter: Filter) -> Route {
+    Route {
+        list: Some(list.into()),
+        ..route(filter, None)
+    }
+}
+
+#[test]
+fn parses_filters() {
+    assert_eq!(Route::parse(""), route(Filter::All, None));
+    assert_eq!(Route::parse("#/"), route(Filter::All, None));
+    assert_eq!(Route::parse("#/active"), route(Filter::Active, None));
+    assert_eq!(Route::parse("#/completed"), route(Filter::Completed, None));
+    assert_eq!(Route::parse("#/overdue"), route(Filter::Overdue, None));
+}
+
+#[test]
+fn parses_entry_links() {
+    assert_eq!(Route::parse("#/entry/42"), route(Filter::All, Some(42)));
+    assert_eq!(
+        Route::parse("#/completed/entry/7"),
+        route(Filter::Completed, Some(7))
+    );
+    assert_eq!(Route::parse("#/entry/nope"), route(Filter::All, None));
+}
+
+#[test]
+fn parses_tags() {
+    assert_eq!(
+        Route::parse("#/tag/work"),
+        tagged(Filter::All, "work", None)
+    );
+    assert_eq!(
+        Route::parse("#/active/tag/work/entry/3"),
+        tagged(Filter::Active, "work", Some(3))
+    );
+    assert_eq!(
+        Route::parse("#/tag/caf%C3%A9%2Fbar"),
+        tagged(Filter::All, "café/bar", None)
+    );
+    assert_eq!(Route::parse("#/tag/"), route(Filter::All, None));
+}
+
+#[test]
+fn parses_lists() {
+    assert_eq!(Route::parse("#/list/work"), in_list("work", Filter::All));
+    assert_eq!(
+        Route::parse("#/list/Big%20Shop/active/tag/dairy"),
+        Route {
+            tag: Some("dairy".into()),
+            ..in_list("Big Shop", Filter::Active)
+        }
+    );
+    assert_eq!(Route::parse("#/list/"), route(Filter::All, None));
+}
+
+#[test]
+fn shows_the_list_by_name() {
+    let mut state = State::new(Persisted::default());
+    state.apply(
+        0.0,
+        &Action::AddList {
+            id: 1,
+            name: "Work".into(),
+        },
+    );
+    in_list("work", Filter::Active).show_in(&mut state);
+    assert_eq!((state.list, state.filter.clone()), (1, Filter::Active));
+    assert_eq!(Route::of(&state), in_list("Work", Filter::Active));
+
+    // A list renamed since falls back to the first one.
+    in_list("Chores", Filter::All).show_in(&mut state);
+    assert_eq!(state.list, DEFAULT_LIST);
+}
+
+fn sorted(filter: Filter, by: Sort, descending: bool) -> Route {
+    Route {
+        sort: Some(SortOrder { by, descending }),
+        ..route(filter, None)
+    }
+}
+
+#[test]
+fn parses_sorts() {
+    assert_eq!(
+        Route::parse("#/sort/due"),
+        sorted(Filter::All, Sort::Due, false)
+    );
+    assert_eq!(
+        Route::parse("#/active/sort/modified-desc/tag/work"),
+        Route {
+            tag: Some("work".into()),
+            ..sorted(Filter::Active, Sort::Modified, true)
+        }
+    );
+    assert_eq!(Route::parse("#/sort/shuffle"), route(Filter::All, None));
+}
+
+#[test]
+fn sorts_the_list_shown() {
+    let mut state = State::new(Persisted::default());
+    state.apply(
+        0.0,
+        &Action::AddList {
+            id: 1,
+            name: "Work".into(),
+        },
+    );
+    Route {
+        list: Some("Work".into()),
+        ..sorted(Filter::All, Sort::Alphabetical, true)
+    }
+    .show_in(&mut state);
+    assert_eq!(
+        Route::of(&state).sort,
+        Some(SortOrder {
+            by: Sort::Alphabetical,
+            descending: true,
+        })
+    );
+
+    // Without a sort, the list keeps the one it had.
+    in_list("Todos", Filter::All).show_in(&mut state);
+    assert_eq!(Route::of(&state).sort, None);
+    in_list("Work", Filter::All).show_in(&mut state);
+    assert_eq!(state.sort().by, Sort::Alphabetical);
+}
+
+#[test]
+fn ignores_unknown_routes() {
+    assert_eq!(Route::parse("#/archived"), route(Filter::All, None));
+}
+
+#[test]
+fn formats_tags_as_one_segment() {
+    assert_eq!(
+        tagged(Filter::Active, "a b/c", Some(2)).to_string(),
+        "#/active/tag/a%20b%2Fc/entry/2"
+    );
+}
+
+#[test]
+fn formats_what_it_parses() {
+    for route in [
+        route(Filter::All, None),
+        route(Filter::Active, None),
+        route(Filter::Completed, Some(3)),
+        route(Filter::All, Some(12)),
+        route(Filter::Today, None),
+        route(Filter::Upcoming, Some(5)),
+        tagged(Filter::Completed, "work", Some(1)),
+        tagged(Filter::All, "50% off/now", None),
+        in_list("Big Shop", Filter::Overdue),
+        sorted(Filter::Completed, Sort::Created, false),
+        sorted(Filter::All, Sort::Priority, true),
+    ] {
+        assert_eq!(Route::parse(&route.to_string()), route);
+    }
+}
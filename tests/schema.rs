@@ -0,0 +1,214 @@
+//! Loads fixtures of every historical storage format.
+
+use yew_wasm_pack_tw_template::model::{Entry, List, Priority, DEFAULT_LIST};
+use yew_wasm_pack_tw_template::schema::{self, SchemaError, CURRENT_VERSION};
+
+fn entry(id: u64, description: &str, completed: bool) -> Entry {
+    Entry {
+        id,
+        description: description.into(),
+        completed,
+        ..Entry::default()
+    }
+}
+
+#[test]
+fn loads_v0_without_ids() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v0.json")).unwrap();
+    assert_eq!(
+        persisted.entries,
+        vec![entry(1, "Buy milk", false), entry(2, "Walk the dog", true)]
+    );
+    assert_eq!(persisted.next_id, 3);
+}
+
+#[test]
+fn loads_v0_with_ids() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v0_with_ids.json")).unwrap();
+    assert_eq!(
+        persisted.entries,
+        vec![entry(3, "Buy milk", false), entry(7, "Walk the dog", true)]
+    );
+    assert_eq!(persisted.next_id, 8);
+}
+
+#[test]
+fn loads_v1() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v1.json")).unwrap();
+    assert_eq!(
+        persisted.entries,
+        vec![entry(3, "Buy milk", false), entry(7, "Walk the dog", true)]
+    );
+    assert_eq!(persisted.next_id, 9);
+}
+
+#[test]
+fn loads_v2() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v2.json")).unwrap();
+    let due = "2024-05-01T17:30".parse().ok();
+    assert_eq!(
+        persisted.entries,
+        vec![
+            Entry {
+                due,
+                ..entry(3, "Buy milk", false)
+            },
+            entry(7, "Walk the dog", true)
+        ]
+    );
+}
+
+#[test]
+fn loads_v3() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v3.json")).unwrap();
+    assert_eq!(
+        persisted.entries,
+        vec![
+            Entry {
+                priority: Priority::High,
+                ..entry(3, "Buy milk", false)
+            },
+            entry(7, "Walk the dog", true)
+        ]
+    );
+}
+
+#[test]
+fn loads_v4() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v4.json")).unwrap();
+    assert_eq!(persisted.entries[0].tags, ["errands", "home"]);
+    assert!(persisted.entries[1].tags.is_empty());
+}
+
+#[test]
+fn loads_v5() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v5.json")).unwrap();
+    assert_eq!(persisted.entries[0].context.as_deref(), Some("store"));
+    assert_eq!(persisted.entries[1].context, None);
+}
+
+#[test]
+fn loads_v6() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v6.json")).unwrap();
+    let milk = &persisted.entries[0];
+    assert_eq!(milk.progress(), Some((1, 2)));
+    assert_eq!(milk.subtasks[1].description, "Soy");
+    assert!(milk.auto_complete);
+    assert_eq!(persisted.entries[1].progress(), None);
+    assert_eq!(persisted.lists, [List::default()]);
+    assert!(persisted.entries.iter().all(|e| e.list == DEFAULT_LIST));
+}
+
+#[test]
+fn loads_v7() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v7.json")).unwrap();
+    let names: Vec<_> = persisted.lists.iter().map(|l| l.name.as_str()).collect();
+    assert_eq!(names, ["Todos", "Work"]);
+    assert_eq!(persisted.entries[0].list, 11);
+    assert_eq!(persisted.entries[1].list, DEFAULT_LIST);
+    assert!(persisted.entries.iter().all(|e| e.updated_at.is_none()));
+}
+
+#[test]
+fn loads_v8() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v8.json")).unwrap();
+    assert_eq!(persisted.entries[0].updated_at, Some(1_714_557_600_000.0));
+    assert_eq!(persisted.entries[1].updated_at, None);
+    let unknown = |e: &Entry| e.created_at.is_none() && e.completed_at.is_none();
+    assert!(persisted.entries.iter().all(unknown));
+}
+
+#[test]
+fn loads_v9() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v9.json")).unwrap();
+    assert!(persisted.entries.iter().all(|e| e.edited_at.is_none()));
+    let milk = &persisted.entries[0];
+    assert_eq!(milk.created_at, Some(1_714_550_400_000.0));
+    assert_eq!(milk.completed_at, None);
+    let dog = &persisted.entries[1];
+    assert_eq!(dog.created_at, None);
+    assert_eq!(dog.completed_at, Some(1_714_561_200_000.0));
+}
+
+#[test]
+fn loads_v10() {
+    let persisted = schema::decode(include_str!("fixtures/schema/v10.json")).unwrap();
+    let milk = &persisted.entries[0];
+    assert_eq!(milk.edited_at, Some(1_714_554_000_000.0));
+    assert_eq!(milk.updated_at, Some(1_714_557_600_000.0));
+    assert_eq!(persisted.entries[1].edited_at, None);
+}
+
+#[test]
+fn rejects_invalid_due_dates() {
+    let raw = r#"{"version":2,"data":{"next_id":2,"entries":[{"id":1,"description":"x","completed":false,"due":"2024-02-30"}]}}"#;
+    assert!(matches!(schema::decode(raw), Err(SchemaError::Json(_))));
+}
+
+#[test]
+fn round_trips_current_version() {
+    let entries = vec![entry(1, "Buy milk", true)];
+    let lists = vec![
+        List::default(),
+        List {
+            id: 2,
+            name: "Work".into(),
+        },
+    ];
+    let encoded = schema::encode(3, &lists, &entries);
+    let persisted = schema::decode(&encoded).unwrap();
+    assert_eq!(persisted.entries, entries);
+    assert_eq!(persisted.lists, lists);
+    assert_eq!(persisted.next_id, 3);
+}
+
+#[test]
+fn rejects_newer_versions() {
+    let raw = format!(r#"{{"version":{},"data":{{}}}}"#, CURRENT_VERSION + 1);
+    match schema::decode(&raw) {
+        Err(SchemaError::UnknownVersion(version)) => assert_eq!(version, CURRENT_VERSION + 1),
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[test]
+fn recovers_entries_from_truncated_payload() {
+    let raw = include_str!("fixtures/schema/truncated.json");
+    assert!(schema::decode(raw).is_err());
+    let persisted = schema::recover(raw);
+    assert_eq!(
+        persisted.entries,
+        vec![
+            entry(3, "Buy milk", false),
+            entry(7, "Walk the {dog}", true),
+            entry(8, "Call mum", false),
+        ]
+    );
+    assert_eq!(persisted.next_id, 9);
+}
+
+#[test]
+fn recovers_subtasks_with_their_entry() {
+    let raw = include_str!("fixtures/schema/v6.json");
+    let truncated = &raw[..raw.find("Walk the dog").unwrap()];
+    let persisted = schema::recover(truncated);
+    assert_eq!(persisted.entries.len(), 1);
+    assert_eq!(persisted.entries[0].subtasks.len(), 2);
+}
+
+#[test]
+fn recovers_lists() {
+    let raw = include_str!("fixtures/schema/v7.json");
+    let truncated = &raw[..raw.find("Walk the dog").unwrap()];
+    let persisted = schema::recover(truncated);
+    assert_eq!(persisted.lists.len(), 2);
+    assert_eq!(persisted.entries.len(), 1);
+    assert_eq!(persisted.entries[0].list, 11);
+    assert_eq!(persisted.next_id, 12);
+}
+
+#[test]
+fn recovers_nothing_from_garbage() {
+    let persisted = schema::recover("not json at all");
+    assert!(persisted.entries.is_empty());
+}
@@ -0,0 +1,39 @@
+use yew_wasm_pack_tw_template::model::{Filter, Priority, Sort, SortOrder};
+use yew_wasm_pack_tw_template::session::Session;
+
+#[test]
+fn round_trips() {
+    let session = Session {
+        list: 7,
+        filter: Filter::Completed,
+        sorts: [(
+            7,
+            SortOrder {
+                by: Sort::Due,
+                descending: true,
+            },
+        )]
+        .into_iter()
+        .collect(),
+        priority_filter: Some(Priority::Low),
+        tag_filter: Some("work".into()),
+        collapsed: [1, 3].into_iter().collect(),
+        value: "Buy mi".into(),
+        editing: Some(3),
+        edit_value: "Walk the d".into(),
+    };
+    assert_eq!(Session::decode(&session.encode()), session);
+}
+
+#[test]
+fn fills_in_missing_fields() {
+    let session = Session::decode(r#"{"filter":"Active"}"#);
+    assert_eq!(session.filter, Filter::Active);
+    assert!(session.sorts.is_empty());
+    assert_eq!(session.editing, None);
+}
+
+#[test]
+fn falls_back_to_defaults_on_garbage() {
+    assert_eq!(Session::decode("{not json"), Session::default());
+}
@@ -0,0 +1,29 @@
+use yew_wasm_pack_tw_template::storage::{Backend, MemoryStorage, Storage};
+
+#[test]
+fn reads_backend_override_from_query() {
+    assert_eq!(
+        Backend::from_query("?storage=session"),
+        Some(Backend::Session)
+    );
+    assert_eq!(
+        Backend::from_query("?lang=en&storage=memory"),
+        Some(Backend::Memory)
+    );
+    assert_eq!(
+        Backend::from_query("?storage=indexeddb"),
+        Some(Backend::IndexedDb)
+    );
+    assert_eq!(Backend::from_query("?storage=cloud"), None);
+    assert_eq!(Backend::from_query(""), None);
+}
+
+#[test]
+fn memory_storage_round_trips() {
+    let mut storage = MemoryStorage::default();
+    assert_eq!(storage.load("key"), None);
+    storage.store("key", "value").unwrap();
+    assert_eq!(storage.load("key").as_deref(), Some("value"));
+    storage.remove("key");
+    assert_eq!(storage.load("key"), None);
+}
@@ -0,0 +1,82 @@
+use yew_wasm_pack_tw_template::model::{Entry, List};
+use yew_wasm_pack_tw_template::validation::{self, Invalid, MAX_LENGTH};
+
+fn entry(id: u64, description: &str) -> Entry {
+    Entry {
+        id,
+        description: description.into(),
+        ..Entry::default()
+    }
+}
+
+#[test]
+fn trims_descriptions() {
+    assert_eq!(
+        validation::description("  Buy milk \n").unwrap(),
+        "Buy milk"
+    );
+}
+
+#[test]
+fn rejects_blank_descriptions() {
+    assert_eq!(validation::description(""), Err(Invalid::Empty));
+    assert_eq!(validation::description(" \t\n"), Err(Invalid::Empty));
+}
+
+#[test]
+fn limits_the_length_in_characters() {
+    let longest = "ä".repeat(MAX_LENGTH);
+    assert_eq!(validation::description(&longest).unwrap(), longest);
+    let too_long = format!("{}ä", longest);
+    assert_eq!(
+        validation::description(&too_long),
+        Err(Invalid::TooLong {
+            length: MAX_LENGTH + 1
+        })
+    );
+}
+
+#[test]
+fn finds_duplicates_ignoring_case_and_the_entry_itself() {
+    let entries = [entry(1, "Buy milk"), entry(2, "Walk the dog")];
+    assert_eq!(
+        validation::duplicate(&entries, " buy MILK", None).map(|e| e.id),
+        Some(1)
+    );
+    assert!(validation::duplicate(&entries, "Buy milk", Some(1)).is_none());
+    assert!(validation::duplicate(&entries, "Buy bread", None).is_none());
+    assert!(validation::duplicate(&entries, "  ", None).is_none());
+}
+
+#[test]
+fn normalizes_tags() {
+    assert_eq!(validation::tag(" #Work ").as_deref(), Some("work"));
+    assert_eq!(
+        validation::tag("Home  Office").as_deref(),
+        Some("home-office")
+    );
+    assert_eq!(validation::tag("#"), None);
+    assert_eq!(validation::tag(&"x".repeat(41)), None);
+}
+
+#[test]
+fn list_names_are_unique_ignoring_case() {
+    let lists = [
+        List::default(),
+        List {
+            id: 1,
+            name: "Work".into(),
+        },
+    ];
+    assert_eq!(
+        validation::list_name(&lists, "  Side   projects ", None).as_deref(),
+        Some("Side projects")
+    );
+    assert_eq!(validation::list_name(&lists, "todos", None), None);
+    assert_eq!(
+        validation::list_name(&lists, "WORK", Some(1)).as_deref(),
+        Some("WORK")
+    );
+    assert_eq!(validation::list_name(&lists, " ", None), None);
+    assert_eq!(validation::list_name(&lists, &"x".repeat(41), None), None);
+}
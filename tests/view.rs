@@ -0,0 +1,323 @@
+//! Snapshot tests of the list markup.
+//!
+//! Each view is rendered to indented HTML and compared with the file of the
+//! same name in `tests/snapshots`, so markup and class changes show up in
+//! review as diffs. After an intended change, rewrite the snapshots with
+//! `UPDATE_SNAPSHOTS=1 cargo test --test view` and commit them.
+
+use std::path::PathBuf;
+use yew::virtual_dom::{Listener, VNode};
+use yew::{Callback, Html, NodeRef};
+use yew_wasm_pack_tw_template::app::Msg;
+use yew_wasm_pack_tw_template::due::Now;
+use yew_wasm_pack_tw_template::events::Action;
+use yew_wasm_pack_tw_template::model::{Entry, Filter, Sort, SortOrder, State, Subtask};
+use yew_wasm_pack_tw_template::schema::Persisted;
+use yew_wasm_pack_tw_template::validation;
+use yew_wasm_pack_tw_template::view;
+
+/// Elements that have no closing tag.
+const VOID: [&str; 4] = ["br", "hr", "img", "input"];
+
+fn render(html: &Html) -> String {
+    let mut out = String::new();
+    write_node(&mut out, html, 0);
+    out
+}
+
+fn write_node(out: &mut String, node: &VNode, depth: usize) {
+    let indent = "  ".repeat(depth);
+    match node {
+        VNode::VTag(tag) => {
+            let mut attributes: Vec<String> = tag
+                .attributes
+                .iter()
+                .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+                .collect();
+            attributes.extend(
+                tag.kind
+                    .iter()
+                    .map(|kind| format!("type=\"{}\"", escape(kind))),
+            );
+            attributes.extend(
+                tag.value
+                    .iter()
+                    .map(|value| format!("value=\"{}\"", escape(value))),
+            );
+            if tag.checked {
+                attributes.push("checked".into());
+            }
+            // Listeners can't be rendered, but which events are handled is
+            // still worth seeing.
+            attributes.extend(
+                tag.listeners
+                    .iter()
+                    .map(|l| Listener::kind(&**l).to_string()),
+            );
+            attributes.sort();
+            let mut open = format!("{}<{}", indent, tag.tag());
+            for attribute in attributes {
+                open.push(' ');
+                open.push_str(&attribute);
+            }
+            out.push_str(&open);
+            out.push_str(">\n");
+            if VOID.contains(&tag.tag()) {
+                return;
+            }
+            for child in tag.children.iter() {
+                write_node(out, child, depth + 1);
+            }
+            out.push_str(&format!("{}</{}>\n", indent, tag.tag()));
+        }
+        VNode::VText(text) => out.push_str(&format!("{}{}\n", indent, escape(&text.text))),
+        VNode::VList(list) => {
+            for child in list.iter() {
+                write_node(out, child, depth);
+            }
+        }
+        VNode::VComp(_) => out.push_str(&format!("{}<!-- component -->\n", indent)),
+        VNode::VRef(_) => out.push_str(&format!("{}<!-- node -->\n", indent)),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn assert_snapshot(name: &str, html: Html) {
+    let rendered = render(&html);
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "snapshots", name]
+        .iter()
+        .collect();
+    let path = path.with_extension("html");
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(&path, &rendered).unwrap();
+        return;
+    }
+    let expected = std::fs::read_to_string(&path).unwrap_or_default();
+    assert!(
+        rendered == expected,
+        "{} doesn't match the rendered markup; rerun with UPDATE_SNAPSHOTS=1 if the change is \
+         intended.\n\nrendered:\n{}",
+        path.display(),
+        rendered
+    );
+}
+
+fn send() -> Callback<Msg> {
+    Callback::from(|_| ())
+}
+
+/// 2024-05-01, 12:00 UTC, as a timestamp.
+const NOON: f64 = 1_714_564_800_000.0;
+const HOUR: f64 = 3_600_000.0;
+
+fn state() -> State {
+    let mut state = State::new(Persisted {
+        next_id: 6,
+        entries: vec![
+            Entry {
+                id: 1,
+                description: "Buy milk".into(),
+                tags: vec!["errands".into(), "home".into()],
+                context: Some("store".into()),
+                created_at: Some(NOON - 50.0 * HOUR),
+                updated_at: Some(NOON - 2.0 * HOUR),
+                edited_at: Some(NOON - 3.0 * HOUR),
+                ..Entry::default()
+            },
+            Entry {
+                id: 2,
+                description: "Walk the <dog>".into(),
+                completed: true,
+                created_at: Some(NOON - HOUR),
+                updated_at: Some(NOON - 0.25 * HOUR),
+                completed_at: Some(NOON - 0.25 * HOUR),
+                ..Entry::default()
+            },
+            Entry {
+                id: 3,
+                description: "Call mum".into(),
+                subtasks: vec![
+                    Subtask {
+                        id: 4,
+                        description: "Find her number".into(),
+                        completed: true,
+                    },
+                    Subtask {
+                        id: 5,
+                        description: "Ask about Sunday".into(),
+                        completed: false,
+                    },
+                ],
+                auto_complete: true,
+                ..Entry::default()
+            },
+        ],
+        ..Persisted::default()
+    });
+    state.now.timestamp = NOON;
+    state
+}
+
+#[test]
+fn todos() {
+    let mut state = state();
+    state.filter = Filter::Active;
+    assert_snapshot(
+        "todos",
+        view::todos(&state, Some(3), &NodeRef::default(), &send()),
+    );
+}
+
+#[test]
+fn todos_when_empty() {
+    let state = State::new(Persisted::default());
+    assert_snapshot(
+        "todos_empty",
+        view::todos(&state, None, &NodeRef::default(), &send()),
+    );
+}
+
+#[test]
+fn input_with_draft() {
+    let mut state = state();
+    state.value = "Water plants".into();
+    assert_snapshot("input", view::input(&state, &send()));
+}
+
+#[test]
+fn input_preview() {
+    let mut state = state();
+    state.now = Now {
+        date: "2024-05-01".parse().unwrap(),
+        time: "12:00".parse().unwrap(),
+        ..state.now
+    };
+    state.value = "Water plants tomorrow 9am !medium #home @garden".into();
+    assert_snapshot("input_preview", view::input(&state, &send()));
+}
+
+#[test]
+fn input_feedback() {
+    let mut state = state();
+    state.value_error = validation::description(" ").err();
+    assert_snapshot("input_error", view::input(&state, &send()));
+    state.value_error = None;
+    state.value = "buy milk".into();
+    assert_snapshot("input_duplicate", view::input(&state, &send()));
+}
+
+#[test]
+fn entries() {
+    let mut state = state();
+    let entries = state.entries().to_vec();
+    assert_snapshot(
+        "entry_active",
+        view::entry(&state, &entries[0], None, &NodeRef::default(), &send()),
+    );
+    assert_snapshot(
+        "entry_completed",
+        view::entry(&state, &entries[1], None, &NodeRef::default(), &send()),
+    );
+    assert_snapshot(
+        "entry_linked",
+        view::entry(&state, &entries[2], Some(3), &NodeRef::default(), &send()),
+    );
+    state.now = Now {
+        date: "2024-05-01".parse().unwrap(),
+        time: "12:00".parse().unwrap(),
+        ..state.now
+    };
+    let overdue = Entry {
+        due: "2024-05-01T09:30".parse().ok(),
+        ..entries[0].clone()
+    };
+    assert_snapshot(
+        "entry_overdue",
+        view::entry(&state, &overdue, None, &NodeRef::default(), &send()),
+    );
+    state.collapsed.insert(3);
+    assert_snapshot(
+        "entry_collapsed",
+        view::entry(&state, &entries[2], None, &NodeRef::default(), &send()),
+    );
+    state.toggle_edit(1);
+    state.edit_value = "Buy oat milk".into();
+    assert_snapshot(
+        "entry_editing",
+        view::entry(&state, &entries[0], None, &NodeRef::default(), &send()),
+    );
+}
+
+#[test]
+fn dragged_entries() {
+    let mut state = state();
+    let entries = state.entries().to_vec();
+    state.dragging = Some(2);
+    assert_snapshot(
+        "entry_dragging",
+        view::entry(&state, &entries[1], None, &NodeRef::default(), &send()),
+    );
+    state.dragging = None;
+    state.set_sort(Sort::Due.into());
+    assert_snapshot(
+        "entry_sorted",
+        view::entry(&state, &entries[1], None, &NodeRef::default(), &send()),
+    );
+}
+
+#[test]
+fn filters() {
+    let mut state = state();
+    state.filter = Filter::Completed;
+    assert_snapshot("filter_selected", view::filter(&state, Filter::Completed));
+    assert_snapshot("filter", view::filter(&state, Filter::Active));
+}
+
+#[test]
+fn sort_descending() {
+    let mut state = state();
+    state.set_sort(SortOrder {
+        by: Sort::Modified,
+        descending: true,
+    });
+    assert_snapshot("sort_descending", view::sort(&state, &send()));
+}
+
+#[test]
+fn tag_filters() {
+    let mut state = state();
+    assert_snapshot("tag_filter_none", view::tag_filter(&state));
+    state.filter = Filter::Active;
+    state.tag_filter = Some("errands".into());
+    assert_snapshot("tag_filter", view::tag_filter(&state));
+}
+
+#[test]
+fn lists() {
+    let mut state = state();
+    assert_snapshot(
+        "lists_one",
+        view::lists(&state, &NodeRef::default(), &send()),
+    );
+    state.apply(
+        0.0,
+        &Action::AddList {
+            id: 6,
+            name: "Work & play".into(),
+        },
+    );
+    state.apply(0.0, &Action::MoveEntry { id: 1, list: 6 });
+    state.list = 6;
+    assert_snapshot("lists", view::lists(&state, &NodeRef::default(), &send()));
+    state.renaming = Some(6);
+    assert_snapshot(
+        "lists_renaming",
+        view::lists(&state, &NodeRef::default(), &send()),
+    );
+}
@@ -1,13 +1,570 @@
 //! Test suite for the Web and headless browsers.
+//!
+//! These mount the real `App` and drive it through DOM events, so they
+//! cover the wiring between the markup and the model that the native tests
+//! can't. Run them with `wasm-pack test --headless --firefox` (or
+//! `--chrome`).
 
 #![cfg(target_arch = "wasm32")]
 
 extern crate wasm_bindgen_test;
+use js_sys::Promise;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
 use wasm_bindgen_test::*;
+use web_sys::{
+    Element, Event, HtmlInputElement, HtmlSelectElement, KeyboardEvent, KeyboardEventInit,
+};
+use yew::prelude::*;
+use yew_wasm_pack_tw_template::app::App;
+use yew_wasm_pack_tw_template::events::{Action, EventLog};
+use yew_wasm_pack_tw_template::model::{Filter, Priority, Sort, SortOrder, State, DEFAULT_LIST};
+use yew_wasm_pack_tw_template::route::Route;
+use yew_wasm_pack_tw_template::schema::{self, Persisted};
+use yew_wasm_pack_tw_template::session::{Session, SESSION_KEY};
+use yew_wasm_pack_tw_template::storage::IdbStore;
 
 wasm_bindgen_test_configure!(run_in_browser);
 
+const KEY: &str = "yew.todomvc.self";
+
+/// Renders `App` until it is told to unmount it, which drops the app and
+/// with it the window listeners it registered.
+struct Harness {
+    mounted: bool,
+}
+
+impl Component for Harness {
+    type Message = ();
+    type Properties = ();
+
+    fn create(_: Self::Properties, _: ComponentLink<Self>) -> Self {
+        Harness { mounted: true }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        self.mounted = false;
+        true
+    }
+
+    fn change(&mut self, _: Self::Properties) -> ShouldRender {
+        false
+    }
+
+    fn view(&self) -> Html {
+        if self.mounted {
+            html! { <App /> }
+        } else {
+            html! {}
+        }
+    }
+}
+
+/// An `App` mounted into a fresh element, starting from empty storage and
+/// the default route. The element is attached to the page so that focus
+/// works, and both are removed when dropped.
+struct Mounted {
+    root: Element,
+    link: ComponentLink<Harness>,
+}
+
+impl Mounted {
+    fn new() -> Self {
+        local_storage().clear().unwrap();
+        Route::default().replace();
+        Mounted::mount()
+    }
+
+    /// An `App` mounted on whatever storage and URL the test set up.
+    fn mount() -> Self {
+        let root = document().create_element("div").unwrap();
+        document().body().unwrap().append_child(&root).unwrap();
+        let link = yew::App::<Harness>::new().mount(root.clone());
+        Mounted { root, link }
+    }
+
+    fn find(&self, selector: &str) -> Element {
+        self.root
+            .query_selector(selector)
+            .unwrap()
+            .unwrap_or_else(|| panic!("nothing matches {}", selector))
+    }
+
+    fn labels(&self) -> Vec<String> {
+        let labels = self
+            .root
+            .query_selector_all("li[id^='entry-'] label")
+            .unwrap();
+        (0..labels.length())
+            .filter_map(|idx| labels.get(idx))
+            .filter_map(|label| label.text_content())
+            .collect()
+    }
+
+    fn type_into(&self, selector: &str, text: &str) {
+        let input: HtmlInputElement = self.find(selector).dyn_into().unwrap();
+        input.set_value(text);
+        input.dispatch_event(&Event::new("input").unwrap()).unwrap();
+    }
+
+    fn press_enter(&self, selector: &str) {
+        self.press(selector, "keypress", "Enter");
+    }
+
+    fn press(&self, selector: &str, event: &str, key: &str) {
+        self.press_with(selector, event, key, KeyboardEventInit::new());
+    }
+
+    fn press_alt(&self, selector: &str, key: &str) {
+        let init = KeyboardEventInit::new();
+        init.set_alt_key(true);
+        self.press_with(selector, "keydown", key, init);
+    }
+
+    fn press_with(&self, selector: &str, event: &str, key: &str, init: KeyboardEventInit) {
+        init.set_key(key);
+        init.set_bubbles(true);
+        let event = KeyboardEvent::new_with_keyboard_event_init_dict(event, &init).unwrap();
+        self.find(selector).dispatch_event(&event).unwrap();
+    }
+
+    fn fire(&self, selector: &str, event: &str) {
+        let event = Event::new(event).unwrap();
+        self.find(selector).dispatch_event(&event).unwrap();
+    }
+
+    fn add(&self, description: &str) {
+        self.type_into(NEW_TODO, description);
+        self.press_enter(NEW_TODO);
+    }
+
+    /// What was written to storage, in the current schema.
+    fn stored(&self) -> Persisted {
+        let raw = local_storage()
+            .get_item(KEY)
+            .unwrap()
+            .expect("nothing stored");
+        schema::decode(&raw).unwrap()
+    }
+}
+
+impl Drop for Mounted {
+    fn drop(&mut self) {
+        self.link.send_message(());
+        self.root.remove();
+    }
+}
+
+const NEW_TODO: &str = "input[placeholder='What needs to be done?']";
+
+fn document() -> web_sys::Document {
+    web_sys::window().unwrap().document().unwrap()
+}
+
+fn local_storage() -> web_sys::Storage {
+    web_sys::window().unwrap().local_storage().unwrap().unwrap()
+}
+
+/// The location hash, as the app last left it.
+fn hash() -> String {
+    web_sys::window().unwrap().location().hash().unwrap()
+}
+
+/// Waits for the app to handle whatever it is waiting for, like IndexedDB
+/// loading, until `done` holds.
+async fn wait_until(done: impl Fn() -> bool) {
+    for _ in 0..100 {
+        if done() {
+            return;
+        }
+        let later = Promise::new(&mut |resolve, _| {
+            web_sys::window()
+                .unwrap()
+                .set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, 10)
+                .unwrap();
+        });
+        JsFuture::from(later).await.unwrap();
+    }
+    panic!("timed out");
+}
+
+/// Follows a link by changing the location hash, as clicking it would, and
+/// waits until the `hashchange` event has been handled.
+async fn follow(href: &str) {
+    let window = web_sys::window().unwrap();
+    let changed = Promise::new(&mut |resolve, _| window.set_onhashchange(Some(&resolve)));
+    window.location().set_hash(href).unwrap();
+    JsFuture::from(changed).await.unwrap();
+    window.set_onhashchange(None);
+}
+
+#[wasm_bindgen_test]
+fn adds_todos_typed_into_the_input() {
+    let app = Mounted::new();
+    app.add("Buy milk");
+    app.add("Walk the dog");
+
+    assert_eq!(app.labels(), ["Buy milk", "Walk the dog"]);
+    let input: HtmlInputElement = app.find(NEW_TODO).dyn_into().unwrap();
+    assert_eq!(input.value(), "");
+    let stored = app.stored();
+    let descriptions: Vec<_> = stored.entries.iter().map(|e| &e.description).collect();
+    assert_eq!(descriptions, ["Buy milk", "Walk the dog"]);
+    assert!(stored.entries.iter().all(|e| e.created_at.is_some()));
+    let label = app.find(&format!("#entry-{} label", stored.entries[0].id));
+    assert_eq!(
+        label.get_attribute("title").as_deref(),
+        Some("Created just now")
+    );
+}
+
 #[wasm_bindgen_test]
-fn pass() {
-    assert_eq!(1 + 1, 2);
+fn rejects_blank_todos() {
+    let app = Mounted::new();
+    app.add("   ");
+
+    assert!(app.labels().is_empty());
+    let error = app.find("header p.text-red-600").text_content().unwrap();
+    assert_eq!(error, "Type something to add a todo.");
+    app.type_into(NEW_TODO, "Buy milk");
+    assert!(app
+        .root
+        .query_selector("header p.text-red-600")
+        .unwrap()
+        .is_none());
+}
+
+#[wasm_bindgen_test]
+fn edits_a_todo_after_double_clicking_it() {
+    let app = Mounted::new();
+    app.add("Buy milk");
+    let id = app.stored().entries[0].id;
+    let item = format!("#entry-{}", id);
+
+    app.fire(&format!("{} label", item), "dblclick");
+    let edit = format!("{} input[type='text']", item);
+    app.type_into(&edit, "Buy oat milk");
+    app.press_enter(&edit);
+
+    assert_eq!(app.labels(), ["Buy oat milk"]);
+    assert!(app.root.query_selector(&edit).unwrap().is_none());
+    assert_eq!(app.stored().entries[0].description, "Buy oat milk");
+}
+
+#[wasm_bindgen_test]
+fn escape_cancels_an_edit_and_returns_focus() {
+    let app = Mounted::new();
+    app.add("Buy milk");
+    let item = format!("#entry-{}", app.stored().entries[0].id);
+
+    app.fire(&format!("{} label", item), "dblclick");
+    let edit = format!("{} input[type='text']", item);
+    let input: HtmlInputElement = app.find(&edit).dyn_into().unwrap();
+    assert_eq!(document().active_element(), Some(input.clone().into()));
+    assert_eq!(input.selection_start().unwrap(), Some(0));
+    assert_eq!(input.selection_end().unwrap(), Some(8));
+
+    app.type_into(&edit, "Buy oat milk");
+    app.press(&edit, "keydown", "Escape");
+
+    assert_eq!(app.labels(), ["Buy milk"]);
+    assert_eq!(app.stored().entries[0].description, "Buy milk");
+    assert_eq!(document().active_element(), Some(app.find(&item)));
+}
+
+#[wasm_bindgen_test]
+fn number_keys_set_the_priority() {
+    let app = Mounted::new();
+    app.add("Buy milk");
+    let item = format!("#entry-{}", app.stored().entries[0].id);
+
+    app.press(&item, "keydown", "3");
+    assert_eq!(app.stored().entries[0].priority, Priority::High);
+    app.press(&format!("{} input[type='date']", item), "keydown", "1");
+    assert_eq!(app.stored().entries[0].priority, Priority::High);
+    app.press(&item, "keydown", "0");
+    assert_eq!(app.stored().entries[0].priority, Priority::None);
+}
+
+#[wasm_bindgen_test]
+async fn alt_arrows_move_todos_past_the_visible_ones() {
+    let app = Mounted::new();
+    for description in ["Buy milk", "Walk the dog", "Call mum"] {
+        app.add(description);
+    }
+    let ids: Vec<_> = app.stored().entries.iter().map(|e| e.id).collect();
+    app.fire(
+        &format!("#entry-{} input[type='checkbox']", ids[1]),
+        "click",
+    );
+    follow("#/list/Todos/active").await;
+
+    let item = format!("#entry-{}", ids[0]);
+    app.press_alt(&item, "ArrowDown");
+    assert_eq!(app.labels(), ["Call mum", "Buy milk"]);
+    let stored: Vec<_> = app.stored().entries.iter().map(|e| e.id).collect();
+    assert_eq!(stored, [ids[1], ids[2], ids[0]]);
+    assert_eq!(document().active_element(), Some(app.find(&item)));
+
+    app.press_alt(&item, "ArrowDown");
+    assert_eq!(app.labels(), ["Call mum", "Buy milk"]);
+    app.press_alt(&item, "ArrowUp");
+    assert_eq!(app.labels(), ["Buy milk", "Call mum"]);
+}
+
+#[wasm_bindgen_test]
+fn ctrl_z_leaves_other_inputs_to_the_browser() {
+    let app = Mounted::new();
+    app.add("Buy milk");
+    let ctrl = || {
+        let init = KeyboardEventInit::new();
+        init.set_ctrl_key(true);
+        init
+    };
+    let tag_input = format!(
+        "#entry-{} input[placeholder='+ tag']",
+        app.stored().entries[0].id
+    );
+    app.press_with(&tag_input, "keydown", "z", ctrl());
+    assert_eq!(app.labels(), ["Buy milk"]);
+    app.press_with(NEW_TODO, "keydown", "z", ctrl());
+    assert!(app.labels().is_empty());
+}
+
+#[wasm_bindgen_test]
+fn clearing_a_description_removes_the_todo() {
+    let app = Mounted::new();
+    app.add("Buy milk");
+    let item = format!("#entry-{}", app.stored().entries[0].id);
+
+    app.fire(&format!("{} label", item), "dblclick");
+    let edit = format!("{} input[type='text']", item);
+    app.type_into(&edit, " ");
+    app.press_enter(&edit);
+
+    assert!(app.labels().is_empty());
+    assert!(app.stored().entries.is_empty());
+}
+
+#[wasm_bindgen_test]
+fn checkboxes_complete_todos() {
+    let app = Mounted::new();
+    app.add("Buy milk");
+    app.add("Walk the dog");
+    let ids: Vec<_> = app.stored().entries.iter().map(|e| e.id).collect();
+
+    app.fire(
+        &format!("#entry-{} input[type='checkbox']", ids[0]),
+        "click",
+    );
+    let completed: Vec<_> = app.stored().entries.iter().map(|e| e.completed).collect();
+    assert_eq!(completed, [true, false]);
+
+    app.fire("#toggle_all", "click");
+    assert!(app.stored().entries.iter().all(|e| e.completed));
+    assert!(app
+        .find("button.rounded.p-4:not(.ml-auto)")
+        .text_content()
+        .unwrap()
+        .contains("(2)"));
+}
+
+#[wasm_bindgen_test]
+async fn filter_links_change_the_visible_todos() {
+    let app = Mounted::new();
+    app.add("Buy milk");
+    app.add("Walk the dog");
+    let done = app.stored().entries[1].id;
+    app.fire(&format!("#entry-{} input[type='checkbox']", done), "click");
+
+    for (filter, expected) in [
+        (Filter::Active, vec!["Buy milk"]),
+        (Filter::Completed, vec!["Walk the dog"]),
+        (Filter::All, vec!["Buy milk", "Walk the dog"]),
+    ] {
+        let route = Route {
+            list: Some("Todos".into()),
+            ..Route::from(filter.clone())
+        };
+        let link = app.find(&format!("a[href='{}']", route));
+        follow(&link.get_attribute("href").unwrap()).await;
+        assert_eq!(app.labels(), expected, "under {}", filter);
+        assert_eq!(link.class_name(), "selected");
+    }
+}
+
+#[wasm_bindgen_test]
+async fn tag_chips_filter_by_their_tag() {
+    let app = Mounted::new();
+    app.add("Buy milk");
+    app.add("Walk the dog");
+    let tagged = format!("#entry-{}", app.stored().entries[0].id);
+    let tag_input = format!("{} input[placeholder='+ tag']", tagged);
+    app.type_into(&tag_input, "#Errands");
+    app.press_enter(&tag_input);
+    assert_eq!(app.stored().entries[0].tags, ["errands"]);
+
+    let chip = app.find(&format!("{} a[href='#/list/Todos/tag/errands']", tagged));
+    follow(&chip.get_attribute("href").unwrap()).await;
+    assert_eq!(app.labels(), ["Buy milk"]);
+    follow("#/").await;
+    assert_eq!(app.labels(), ["Buy milk", "Walk the dog"]);
+}
+
+#[wasm_bindgen_test]
+fn quick_add_sets_fields_from_the_description() {
+    let app = Mounted::new();
+    app.type_into(NEW_TODO, "Buy milk #errands !high @store");
+    assert!(app
+        .find("ul[aria-live='polite']")
+        .text_content()
+        .unwrap()
+        .contains("High priority"));
+    app.press_enter(NEW_TODO);
+
+    let entry = &app.stored().entries[0];
+    assert_eq!(entry.description, "Buy milk");
+    assert_eq!(entry.tags, ["errands"]);
+    assert_eq!(entry.priority, Priority::High);
+    assert_eq!(entry.context.as_deref(), Some("store"));
+    assert_eq!(app.labels(), ["Buy milk"]);
+}
+
+#[wasm_bindgen_test]
+fn finishing_the_subtasks_completes_the_todo() {
+    let app = Mounted::new();
+    app.add("Call mum");
+    let item = format!("#entry-{}", app.stored().entries[0].id);
+    let subtask_input = format!("{} input[placeholder='+ subtask']", item);
+    for description in ["Find her number", "Ask about Sunday"] {
+        app.type_into(&subtask_input, description);
+        app.press_enter(&subtask_input);
+    }
+    assert!(app.find(&item).text_content().unwrap().contains("0/2"));
+
+    app.fire(&format!("{} label.mt-1 input", item), "click");
+    for subtask in 1..=2 {
+        app.fire(
+            &format!("{} .ml-12 li:nth-child({}) input", item, subtask),
+            "click",
+        );
+    }
+    let entry = &app.stored().entries[0];
+    assert!(entry.auto_complete);
+    assert!(entry.completed);
+    assert!(app.find(&item).text_content().unwrap().contains("2/2"));
+}
+
+#[wasm_bindgen_test]
+async fn new_lists_keep_their_own_todos() {
+    let app = Mounted::new();
+    app.add("Buy milk");
+    app.type_into("input[placeholder='+ list']", "Work");
+    app.press_enter("input[placeholder='+ list']");
+    assert!(app.labels().is_empty());
+    app.add("Write report");
+    assert_eq!(app.labels(), ["Write report"]);
+
+    let stored = app.stored();
+    let work = stored.lists.iter().find(|l| l.name == "Work").unwrap().id;
+    assert_eq!(stored.entries[1].list, work);
+    follow("#/list/Todos").await;
+    assert_eq!(app.labels(), ["Buy milk"]);
+}
+
+#[wasm_bindgen_test]
+async fn sorting_is_kept_in_the_route() {
+    let app = Mounted::new();
+    app.add("Walk the dog");
+    app.add("Buy milk");
+    let select: HtmlSelectElement = app
+        .find("option[value='alphabetical']")
+        .parent_element()
+        .unwrap()
+        .dyn_into()
+        .unwrap();
+    select.set_value("alphabetical");
+    select
+        .dispatch_event(&Event::new("change").unwrap())
+        .unwrap();
+    assert_eq!(app.labels(), ["Buy milk", "Walk the dog"]);
+    assert_eq!(hash(), "#/list/Todos/sort/alphabetical");
+
+    app.fire("button[title='Ascending']", "click");
+    assert_eq!(app.labels(), ["Walk the dog", "Buy milk"]);
+    assert_eq!(hash(), "#/list/Todos/sort/alphabetical-desc");
+
+    // The list remembers its sort when opened without one.
+    follow("#/list/Todos").await;
+    assert_eq!(app.labels(), ["Walk the dog", "Buy milk"]);
+    assert_eq!(hash(), "#/list/Todos/sort/alphabetical-desc");
+}
+
+#[wasm_bindgen_test]
+async fn restores_the_session_once_indexeddb_has_loaded() {
+    let mut state = State::new(Persisted::default());
+    let work = state.allocate_id();
+    state.apply(
+        0.0,
+        &Action::AddList {
+            id: work,
+            name: "Work".into(),
+        },
+    );
+    for (description, list) in [
+        ("Call mum", DEFAULT_LIST),
+        ("Buy milk", work),
+        ("Walk the dog", work),
+    ] {
+        let id = state.allocate_id();
+        let add = Action::Add {
+            id,
+            description: description.into(),
+            list,
+        };
+        state.apply(0.0, &add);
+    }
+    let (mut idb, _) = IdbStore::open().await.unwrap();
+    // Replaces whatever an earlier run left.
+    idb.invalidate();
+    idb.sync(
+        &state,
+        &EventLog::new(state.to_persisted()),
+        None,
+        Callback::noop(),
+    );
+
+    local_storage().clear().unwrap();
+    let session = Session {
+        list: work,
+        sorts: [(
+            work,
+            SortOrder {
+                by: Sort::Alphabetical,
+                descending: true,
+            },
+        )]
+        .into_iter()
+        .collect(),
+        ..Session::default()
+    };
+    local_storage()
+        .set_item(SESSION_KEY, &session.encode())
+        .unwrap();
+    let location = web_sys::window().unwrap().location();
+    let path = location.pathname().unwrap();
+    let history = web_sys::window().unwrap().history().unwrap();
+    history
+        .replace_state_with_url(&JsValue::NULL, "", Some("?storage=indexeddb"))
+        .unwrap();
+    let app = Mounted::mount();
+    wait_until(|| !app.labels().is_empty()).await;
+
+    assert_eq!(app.labels(), ["Walk the dog", "Buy milk"]);
+    assert_eq!(hash(), "#/list/Work/sort/alphabetical-desc");
+    let stored = local_storage().get_item(SESSION_KEY).unwrap().unwrap();
+    assert_eq!(Session::decode(&stored), session);
+    history
+        .replace_state_with_url(&JsValue::NULL, "", Some(&path))
+        .unwrap();
 }